@@ -16,6 +16,16 @@ pub trait EthNetworkExt: Send + Sync {
             _ => unreachable!()
         }
     }
+
+    /// seconds between blocks
+    fn block_time() -> u64 {
+        match Self::CHAIN_ID {
+            1 | 11155111 => 12,
+            8453 => 2,
+            130 => 1,
+            _ => unreachable!()
+        }
+    }
 }
 
 pub trait AllExtensions: std::fmt::Debug + Send + Sync + Clone + Copy + Unpin + 'static {}
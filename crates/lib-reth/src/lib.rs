@@ -6,6 +6,9 @@ pub mod reth_libmdbx;
 pub mod rpc;
 pub mod traits;
 
+#[cfg(feature = "multi-chain")]
+pub mod multi_chain;
+
 #[cfg(feature = "op-reth-db")]
 pub mod op_reth {
     pub use reth_optimism_chainspec::*;
@@ -0,0 +1,355 @@
+use std::{any::Any, collections::HashMap, sync::Arc};
+
+use alloy_consensus::BlockHeader;
+use alloy_eips::{BlockId, BlockNumHash};
+use alloy_network::{Ethereum, Network, primitives::HeaderResponse};
+use alloy_primitives::{Address, B256, ChainId, StorageKey, StorageValue, TxHash, U256};
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::{Filter, Log};
+use eth_network_exts::{EthNetworkExt, mainnet::MainnetExt, sepolia_testnet::SepoliaTestnetExt};
+use exe_runners::Runtime;
+use futures::{StreamExt, stream::BoxStream};
+use reth_chainspec::{MAINNET, SEPOLIA};
+use revm::{
+    DatabaseRef,
+    state::{AccountInfo, Bytecode}
+};
+use revm_database::CacheDB;
+use uniswap_storage::StorageSlotFetcher;
+
+use crate::{
+    reth_libmdbx::{NodeClientSpec, RethNodeClient, RethNodeClientBuilder},
+    rpc::EthRpcClient,
+    traits::{AsyncEthRevmParams, EthRevm, EthRevmParams, EthStream, reth_revm_utils::RevmUtilError}
+};
+
+/// a chain's [`EthRevm::InnerDb`] with its error type erased
+pub type DynDatabaseRef = Box<dyn DatabaseRef<Error = RevmUtilError> + Send + Sync>;
+
+/// the operations shared by every client in [`MultiChainClients`]. use
+/// [`ChainClient::as_any`] to get back the concrete client
+#[async_trait::async_trait]
+pub trait ChainClient: StorageSlotFetcher + Send + Sync {
+    fn chain_id(&self) -> ChainId;
+
+    fn revm_db(&self, block_id: BlockId) -> eyre::Result<CacheDB<DynDatabaseRef>>;
+
+    /// `newHeads`
+    async fn block_stream(&self) -> eyre::Result<BoxStream<'static, BlockNumHash>>;
+
+    /// `newPendingTransactions` (false)
+    async fn pending_transaction_hashes_stream(&self) -> eyre::Result<BoxStream<'static, TxHash>>;
+
+    /// `logs`
+    async fn log_stream(&self, filter: Filter) -> eyre::Result<BoxStream<'static, Log>>;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+#[derive(Debug, Clone)]
+pub enum ChainClientConfig {
+    Reth {
+        chain_id:            ChainId,
+        db_path:             String,
+        max_tasks:           usize,
+        ipc_path_or_rpc_url: Option<String>,
+        max_read_tx_secs:    Option<u64>
+    },
+    /// `http(s)`, `ws(s)` or `.ipc` endpoint
+    Rpc { chain_id: ChainId, url: String }
+}
+
+/// clients for several chains, all spawning onto one [`Runtime`]
+pub struct MultiChainClients {
+    runtime: Runtime,
+    clients: HashMap<ChainId, Arc<dyn ChainClient>>
+}
+
+impl MultiChainClients {
+    pub fn new(runtime: Runtime) -> Self {
+        Self { runtime, clients: HashMap::new() }
+    }
+
+    pub async fn build(configs: impl IntoIterator<Item = ChainClientConfig>) -> eyre::Result<Self> {
+        Self::build_with_runtime(configs, crate::reth_libmdbx::provider_runtime()?).await
+    }
+
+    pub async fn build_with_runtime(
+        configs: impl IntoIterator<Item = ChainClientConfig>,
+        runtime: Runtime
+    ) -> eyre::Result<Self> {
+        let mut this = Self::new(runtime);
+        for config in configs {
+            let client = match config {
+                ChainClientConfig::Reth { chain_id, db_path, max_tasks, ipc_path_or_rpc_url, max_read_tx_secs } => this
+                    .build_reth_client(chain_id, &db_path, max_tasks, ipc_path_or_rpc_url.as_deref(), max_read_tx_secs)?,
+                ChainClientConfig::Rpc { chain_id, url } => this.build_rpc_client(chain_id, &url).await?
+            };
+            this.insert(client);
+        }
+
+        Ok(this)
+    }
+
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// replaces any client already registered for the same chain
+    pub fn insert(&mut self, client: Arc<dyn ChainClient>) {
+        self.clients.insert(client.chain_id(), client);
+    }
+
+    pub fn get(&self, chain_id: ChainId) -> Option<Arc<dyn ChainClient>> {
+        self.clients.get(&chain_id).cloned()
+    }
+
+    pub fn chain_ids(&self) -> impl Iterator<Item = ChainId> + '_ {
+        self.clients.keys().copied()
+    }
+
+    fn build_reth_client(
+        &self,
+        chain_id: ChainId,
+        db_path: &str,
+        max_tasks: usize,
+        ipc_path_or_rpc_url: Option<&str>,
+        max_read_tx_secs: Option<u64>
+    ) -> eyre::Result<Arc<dyn ChainClient>> {
+        let runtime = self.runtime.clone();
+
+        let client: Arc<dyn ChainClient> = match chain_id {
+            MainnetExt::<()>::CHAIN_ID => Arc::new(
+                RethNodeClientBuilder::<MainnetExt>::new(
+                    db_path,
+                    max_tasks,
+                    MAINNET.clone(),
+                    ipc_path_or_rpc_url,
+                    max_read_tx_secs
+                )
+                .build_with_task_executor(runtime)?
+            ),
+            SepoliaTestnetExt::<()>::CHAIN_ID => Arc::new(
+                RethNodeClientBuilder::<SepoliaTestnetExt>::new(
+                    db_path,
+                    max_tasks,
+                    SEPOLIA.clone(),
+                    ipc_path_or_rpc_url,
+                    max_read_tx_secs
+                )
+                .build_with_task_executor(runtime)?
+            ),
+            #[cfg(feature = "op-reth-db")]
+            eth_network_exts::base_mainnet::BaseMainnetExt::<()>::CHAIN_ID => Arc::new(
+                RethNodeClientBuilder::<eth_network_exts::base_mainnet::BaseMainnetExt>::new(
+                    db_path,
+                    max_tasks,
+                    reth_optimism_chainspec::BASE_MAINNET.clone(),
+                    ipc_path_or_rpc_url,
+                    max_read_tx_secs
+                )
+                .build_with_task_executor(runtime)?
+            ),
+            #[cfg(feature = "op-reth-db")]
+            eth_network_exts::unichain_mainnet::UnichainMainnetExt::<()>::CHAIN_ID => Arc::new(
+                RethNodeClientBuilder::<eth_network_exts::unichain_mainnet::UnichainMainnetExt>::new(
+                    db_path,
+                    max_tasks,
+                    crate::reth_libmdbx::op_node::get_op_superchain_spec("unichain"),
+                    ipc_path_or_rpc_url,
+                    max_read_tx_secs
+                )
+                .build_with_task_executor(runtime)?
+            ),
+            _ => eyre::bail!("no reth node type for chain id {chain_id}")
+        };
+
+        Ok(client)
+    }
+
+    async fn build_rpc_client(&self, chain_id: ChainId, url: &str) -> eyre::Result<Arc<dyn ChainClient>> {
+        let handle = self.runtime.handle().clone();
+
+        let client: Arc<dyn ChainClient> = match chain_id {
+            MainnetExt::<()>::CHAIN_ID | SepoliaTestnetExt::<()>::CHAIN_ID => {
+                Arc::new(RpcChainClient::<Ethereum>::connect(chain_id, url, handle).await?)
+            }
+            #[cfg(feature = "op-reth-db")]
+            eth_network_exts::base_mainnet::BaseMainnetExt::<()>::CHAIN_ID
+            | eth_network_exts::unichain_mainnet::UnichainMainnetExt::<()>::CHAIN_ID => {
+                Arc::new(RpcChainClient::<op_alloy_network::Optimism>::connect(chain_id, url, handle).await?)
+            }
+            _ => eyre::bail!("no alloy network for chain id {chain_id}")
+        };
+
+        Ok(client)
+    }
+}
+
+/// an [`EthRpcClient`] that knows which chain it is connected to
+pub struct RpcChainClient<N: Network> {
+    chain_id: ChainId,
+    client:   EthRpcClient<RootProvider<N>, N>,
+    handle:   tokio::runtime::Handle
+}
+
+impl<N: Network> RpcChainClient<N> {
+    pub async fn connect(chain_id: ChainId, url: &str, handle: tokio::runtime::Handle) -> eyre::Result<Self> {
+        let provider = alloy_provider::builder::<N>().connect(url).await?;
+        let remote_chain_id = provider.get_chain_id().await?;
+        if remote_chain_id != chain_id {
+            eyre::bail!("{url} is connected to chain {remote_chain_id}, expected {chain_id}");
+        }
+
+        Ok(Self { chain_id, client: EthRpcClient::new(provider), handle })
+    }
+
+    pub fn client(&self) -> &EthRpcClient<RootProvider<N>, N> {
+        &self.client
+    }
+}
+
+#[async_trait::async_trait]
+impl<N: Network> StorageSlotFetcher for RpcChainClient<N> {
+    async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+        StorageSlotFetcher::storage_at(self.client.provider(), address, key, block_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<N: Network> ChainClient for RpcChainClient<N> {
+    fn chain_id(&self) -> ChainId {
+        self.chain_id
+    }
+
+    fn revm_db(&self, block_id: BlockId) -> eyre::Result<CacheDB<DynDatabaseRef>> {
+        let params = AsyncEthRevmParams { block_id, chain_id: self.chain_id, handle: self.handle.clone() };
+        Ok(CacheDB::new(Box::new(ErasedDatabaseRef(self.client.make_inner_db(&params)?))))
+    }
+
+    async fn block_stream(&self) -> eyre::Result<BoxStream<'static, BlockNumHash>> {
+        block_stream(self.client.provider().clone()).await
+    }
+
+    async fn pending_transaction_hashes_stream(&self) -> eyre::Result<BoxStream<'static, TxHash>> {
+        Ok(self
+            .client
+            .subscribe_pending_transactions()
+            .await?
+            .into_stream()
+            .boxed())
+    }
+
+    async fn log_stream(&self, filter: Filter) -> eyre::Result<BoxStream<'static, Log>> {
+        Ok(self.client.subscribe_logs(&filter).await?.into_stream().boxed())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<Ext> ChainClient for RethNodeClient<Ext>
+where
+    Ext: EthNetworkExt + 'static,
+    Ext::RethNode: NodeClientSpec
+{
+    fn chain_id(&self) -> ChainId {
+        Ext::CHAIN_ID
+    }
+
+    fn revm_db(&self, block_id: BlockId) -> eyre::Result<CacheDB<DynDatabaseRef>> {
+        let params = EthRevmParams { block_id, chain_id: Ext::CHAIN_ID };
+        Ok(CacheDB::new(Box::new(ErasedDatabaseRef(self.make_inner_db(&params)?))))
+    }
+
+    async fn block_stream(&self) -> eyre::Result<BoxStream<'static, BlockNumHash>> {
+        block_stream(self.root_provider().await?).await
+    }
+
+    async fn pending_transaction_hashes_stream(&self) -> eyre::Result<BoxStream<'static, TxHash>> {
+        let root = self.root_provider().await?;
+        Ok(root.subscribe_pending_transactions().await?.into_stream().boxed())
+    }
+
+    async fn log_stream(&self, filter: Filter) -> eyre::Result<BoxStream<'static, Log>> {
+        let root = self.root_provider().await?;
+        Ok(root.subscribe_logs(&filter).await?.into_stream().boxed())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+async fn block_stream<N: Network>(root: RootProvider<N>) -> eyre::Result<BoxStream<'static, BlockNumHash>> {
+    Ok(root
+        .subscribe_blocks()
+        .await?
+        .into_stream()
+        .map(|header| BlockNumHash::new(header.number(), header.hash()))
+        .boxed())
+}
+
+struct ErasedDatabaseRef<DB>(DB);
+
+impl<DB: DatabaseRef> DatabaseRef for ErasedDatabaseRef<DB> {
+    type Error = RevmUtilError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.0
+            .basic_ref(address)
+            .map_err(|e| RevmUtilError(eyre::eyre!("{e:?}")))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.0
+            .code_by_hash_ref(code_hash)
+            .map_err(|e| RevmUtilError(eyre::eyre!("{e:?}")))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.0
+            .storage_ref(address, index)
+            .map_err(|e| RevmUtilError(eyre::eyre!("{e:?}")))
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.0
+            .block_hash_ref(number)
+            .map_err(|e| RevmUtilError(eyre::eyre!("{e:?}")))
+    }
+}
+
+#[cfg(all(test, not(feature = "ci")))]
+mod tests {
+    use alloy_eips::BlockId;
+    use alloy_primitives::{U256, address};
+
+    use super::*;
+
+    const MAINNET_DB_PATH: &str = "/var/lib/eth/mainnet/reth/";
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_multi_chain_clients() {
+        let clients = MultiChainClients::build([ChainClientConfig::Reth {
+            chain_id:            1,
+            db_path:             MAINNET_DB_PATH.to_string(),
+            max_tasks:           1000,
+            ipc_path_or_rpc_url: None,
+            max_read_tx_secs:    None
+        }])
+        .await
+        .unwrap();
+
+        assert!(clients.get(8453).is_none());
+        let mainnet = clients.get(1).unwrap();
+        assert!(mainnet.as_any().is::<RethNodeClient<MainnetExt>>());
+
+        let weth = address!("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let db = mainnet.revm_db(BlockId::latest()).unwrap();
+        assert!(db.basic_ref(weth).unwrap().unwrap().balance > U256::ZERO);
+    }
+}
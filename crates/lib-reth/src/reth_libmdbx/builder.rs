@@ -3,10 +3,13 @@ use std::{
     sync::Arc
 };
 
+use alloy_primitives::U256;
 use eth_network_exts::EthNetworkExt;
 use exe_runners::Runtime;
 use reth_db::mdbx::{DatabaseArguments, MaxReadTransactionDuration};
 use reth_node_types::NodeTypes;
+use reth_rpc_eth_types::{EthConfig, EthFilterConfig};
+use reth_transaction_pool::PoolConfig;
 
 use crate::reth_libmdbx::node_types::{NodeClientSpec, RethNodeClient};
 
@@ -17,7 +20,9 @@ pub struct RethNodeClientBuilder<Ext: EthNetworkExt> {
     db_args:             Option<DatabaseArguments>,
     chain:               Arc<<Ext::RethNode as NodeTypes>::ChainSpec>,
     max_read_tx_secs:    MaxReadTransactionDuration,
-    ipc_path_or_rpc_url: Option<String>
+    ipc_path_or_rpc_url: Option<String>,
+    forwarding_endpoint: Option<String>,
+    api_config:          ApiConfig
 }
 
 impl<Ext: EthNetworkExt> RethNodeClientBuilder<Ext>
@@ -37,6 +42,8 @@ where
             db_args: None,
             chain,
             ipc_path_or_rpc_url: ipc_path_or_rpc_url.map(|a| a.to_string()),
+            forwarding_endpoint: None,
+            api_config: ApiConfig::default(),
             max_read_tx_secs: max_read_tx_secs
                 .map(|s| MaxReadTransactionDuration::Set(std::time::Duration::from_secs(s)))
                 .unwrap_or(MaxReadTransactionDuration::Unbounded)
@@ -48,6 +55,69 @@ where
         self
     }
 
+    /// the sequencer for OP chains or an rpc/relay url for L1. raw
+    /// transactions sent to the eth api, over the rpc server or with
+    /// [`RethNodeClient::submit_raw_transaction`], are forwarded here. OP
+    /// sequencers must be http endpoints
+    pub fn with_forwarding_endpoint(mut self, forwarding_endpoint: &str) -> Self {
+        self.forwarding_endpoint = Some(forwarding_endpoint.to_string());
+        self
+    }
+
+    pub fn with_eth_config(mut self, eth_config: EthConfig) -> Self {
+        self.api_config.eth_config = eth_config;
+        self
+    }
+
+    /// max gas for `eth_call`, `eth_estimateGas` and `eth_simulateV1`
+    pub fn with_rpc_gas_cap(mut self, rpc_gas_cap: u64) -> Self {
+        self.api_config.eth_config.rpc_gas_cap = rpc_gas_cap;
+        self
+    }
+
+    pub fn with_max_simulate_blocks(mut self, max_simulate_blocks: u64) -> Self {
+        self.api_config.eth_config.rpc_max_simulate_blocks = max_simulate_blocks;
+        self
+    }
+
+    pub fn with_max_trace_filter_blocks(mut self, max_trace_filter_blocks: u64) -> Self {
+        self.api_config.eth_config.max_trace_filter_blocks = max_trace_filter_blocks;
+        self
+    }
+
+    pub fn with_filter_config(mut self, filter_config: EthFilterConfig) -> Self {
+        self.api_config.filter_config = filter_config;
+        self
+    }
+
+    pub fn with_max_blocks_per_filter(mut self, max_blocks_per_filter: u64) -> Self {
+        self.api_config.filter_config = self
+            .api_config
+            .filter_config
+            .max_blocks_per_filter(max_blocks_per_filter);
+        self
+    }
+
+    pub fn with_max_logs_per_response(mut self, max_logs_per_response: usize) -> Self {
+        self.api_config.filter_config = self
+            .api_config
+            .filter_config
+            .max_logs_per_response(max_logs_per_response);
+        self
+    }
+
+    /// pool sizes, price bumps and minimum fees of the local tx pool
+    pub fn with_pool_config(mut self, pool_config: PoolConfig) -> Self {
+        self.api_config.pool_config = pool_config;
+        self
+    }
+
+    /// only used by OP chains
+    pub fn with_min_suggested_priority_fee(mut self, min_suggested_priority_fee: U256) -> Self {
+        self.api_config.min_suggested_priority_fee = min_suggested_priority_fee;
+        self
+    }
+
     pub fn build(self) -> eyre::Result<RethNodeClient<Ext>> {
         self.build_with_task_executor(super::node_types::provider_runtime()?)
     }
@@ -63,10 +133,12 @@ where
 
         <Ext::RethNode as NodeClientSpec>::new_with_db::<Ext>(
             db_config,
+            self.api_config,
             self.max_tasks,
             task_executor,
             self.chain,
-            self.ipc_path_or_rpc_url
+            self.ipc_path_or_rpc_url,
+            self.forwarding_endpoint
         )
     }
 
@@ -101,3 +173,22 @@ pub struct DbConfig {
     pub rocksdb_path:      PathBuf,
     pub db_args:           DatabaseArguments
 }
+
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    pub eth_config:                 EthConfig,
+    pub filter_config:              EthFilterConfig,
+    pub pool_config:                PoolConfig,
+    pub min_suggested_priority_fee: U256
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            eth_config:                 EthConfig::default(),
+            filter_config:              EthFilterConfig::default(),
+            pool_config:                PoolConfig::default(),
+            min_suggested_priority_fee: U256::from(1_000_000u64)
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::ops::RangeInclusive;
+
+use alloy_primitives::{Address, B256, BlockNumber};
+use eth_network_exts::EthNetworkExt;
+use reth_provider::{
+    DatabaseProviderFactory, ProviderResult,
+    providers::{HistoricalStateProviderRef, HistoryInfo, LowestAvailableBlocks}
+};
+
+use crate::reth_libmdbx::{NodeClientSpec, RethNodeClient};
+
+impl<Ext: EthNetworkExt> RethNodeClient<Ext>
+where
+    Ext::RethNode: NodeClientSpec
+{
+    /// every block in `range` where `slot` of `address` changed, read from the
+    /// `StoragesHistory` index wherever the node keeps it (mdbx or rocksdb).
+    /// costs one index seek per change in `range`
+    pub fn storage_slot_history(
+        &self,
+        address: Address,
+        slot: B256,
+        range: RangeInclusive<BlockNumber>
+    ) -> eyre::Result<Vec<BlockNumber>> {
+        let provider = self.eth_db_provider().database_provider_ro()?;
+
+        changed_blocks(range, |block_number| {
+            HistoricalStateProviderRef::new_with_lowest_available_blocks(&provider, block_number, every_block_available())
+                .storage_history_lookup(address, slot)
+        })
+    }
+
+    /// every block in `range` where the balance, nonce or code of `address`
+    /// changed, read from the `AccountsHistory` index. costs one index seek
+    /// per change in `range`
+    pub fn account_history(&self, address: Address, range: RangeInclusive<BlockNumber>) -> eyre::Result<Vec<BlockNumber>> {
+        let provider = self.eth_db_provider().database_provider_ro()?;
+
+        changed_blocks(range, |block_number| {
+            HistoricalStateProviderRef::new_with_lowest_available_blocks(&provider, block_number, every_block_available())
+                .account_history_lookup(address)
+        })
+    }
+}
+
+/// with history available from genesis, a lookup before the first write of a
+/// key still returns the block of that write instead of `NotYetWritten`. on a
+/// pruned node the pruned blocks are simply missing from the index
+fn every_block_available() -> LowestAvailableBlocks {
+    LowestAvailableBlocks { account_history_block_number: Some(0), storage_history_block_number: Some(0) }
+}
+
+/// walks `range` from change to change. `lookup` finds the first block at or
+/// after the one it is given whose changeset has the key
+fn changed_blocks(
+    range: RangeInclusive<BlockNumber>,
+    mut lookup: impl FnMut(BlockNumber) -> ProviderResult<HistoryInfo>
+) -> eyre::Result<Vec<BlockNumber>> {
+    let mut changed_blocks = Vec::new();
+    let mut from = *range.start();
+
+    while from <= *range.end() {
+        match lookup(from)? {
+            HistoryInfo::InChangeset(block_number) if block_number <= *range.end() => {
+                changed_blocks.push(block_number);
+                from = block_number + 1;
+            }
+            _ => break
+        }
+    }
+
+    Ok(changed_blocks)
+}
@@ -3,3 +3,26 @@ pub use node_types::*;
 
 mod builder;
 pub use builder::*;
+
+mod history;
+
+mod pinned_state;
+pub use pinned_state::*;
+
+mod state_diff;
+pub use state_diff::*;
+
+mod submission;
+pub use submission::*;
+
+mod traces;
+
+#[cfg(feature = "revm")]
+mod replay;
+#[cfg(feature = "revm")]
+pub use replay::*;
+
+#[cfg(feature = "rpc-server")]
+mod rpc_server;
+#[cfg(feature = "rpc-server")]
+pub use rpc_server::*;
@@ -1,14 +1,23 @@
 use std::sync::Arc;
 
+use alloy_network::Network;
 use alloy_provider::{IpcConnect, RootProvider, WsConnect, builder};
 use eth_network_exts::EthNetworkExt;
+use reth_chainspec::EthereumHardforks;
 use reth_node_types::NodeTypes;
 use reth_provider::{
-    BlockNumReader, CanonStateSubscriptions, DatabaseProviderFactory, StateProviderFactory, TryIntoHistoricalStateProvider
+    BlockNumReader, BlockReaderIdExt, CanonStateSubscriptions, ChangeSetReader, DBProvider, DatabaseProviderFactory, StateProviderFactory,
+    StorageChangeSetReader, TryIntoHistoricalStateProvider
+};
+use reth_rpc_api::{DebugApiServer, TraceApiServer};
+use reth_rpc_eth_api::{
+    EthApiTypes, EthFilterApiServer, FullEthApiServer, RpcNodeCore, RpcTransaction, RpcTxReq, helpers::FullEthApi
 };
-use reth_rpc_eth_api::{EthApiTypes, FullEthApiServer, RpcNodeCore, helpers::FullEthApi};
 
-use crate::{reth_libmdbx::DbConfig, traits::EthStream};
+use crate::{
+    reth_libmdbx::{ApiConfig, DbConfig},
+    traits::EthStream
+};
 
 pub mod node;
 #[cfg(feature = "op-reth-db")]
@@ -27,14 +36,17 @@ pub(crate) fn provider_runtime() -> eyre::Result<reth_tasks::Runtime> {
     }
 }
 
-pub trait NodeClientSpec: NodeTypes + Send + Sync {
+pub trait NodeClientSpec: NodeTypes<ChainSpec: EthereumHardforks> + Send + Sync {
     type Api: FullEthApi + FullEthApiServer + EthApiTypes + RpcNodeCore + Clone + Send + Sync;
-    type Filter: Clone + Send + Sync;
-    type Trace: Clone + Send + Sync;
-    type Debug: Clone + Send + Sync;
+    type Filter: EthFilterApiServer<RpcTransaction<<Self::Api as EthApiTypes>::NetworkTypes>> + Clone + Send + Sync;
+    type Trace: TraceApiServer<RpcTxReq<<Self::Api as EthApiTypes>::NetworkTypes>> + Clone + Send + Sync;
+    type Debug: DebugApiServer<RpcTxReq<<Self::Api as EthApiTypes>::NetworkTypes>> + Clone + Send + Sync;
     type TxPool: Clone + Send + Sync;
-    type DbProvider: DatabaseProviderFactory<Provider: TryIntoHistoricalStateProvider + BlockNumReader>
+    type DbProvider: DatabaseProviderFactory<
+            Provider: TryIntoHistoricalStateProvider + BlockNumReader + ChangeSetReader + StorageChangeSetReader + DBProvider
+        >
         + StateProviderFactory
+        + BlockReaderIdExt
         + CanonStateSubscriptions
         + Send
         + Sync
@@ -43,13 +55,22 @@ pub trait NodeClientSpec: NodeTypes + Send + Sync {
 
     fn new_with_db<Ext>(
         db_config: DbConfig,
+        api_config: ApiConfig,
         max_tasks: usize,
         task_executor: reth_tasks::Runtime,
         chain: Arc<<Self as NodeTypes>::ChainSpec>,
-        ipc_path_or_rpc_url: Option<String>
+        ipc_path_or_rpc_url: Option<String>,
+        forwarding_endpoint: Option<String>
     ) -> eyre::Result<RethNodeClient<Ext>>
     where
         Ext: EthNetworkExt<RethNode = Self>;
+
+    /// the op evm spec active at `timestamp`. `None` if the chain is not an
+    /// op stack chain
+    #[cfg(feature = "op-revm")]
+    fn op_spec_id_at(_chain_spec: &<Self as NodeTypes>::ChainSpec, _timestamp: u64) -> Option<op_revm::OpSpecId> {
+        None
+    }
 }
 
 pub struct RethNodeClient<Ext: EthNetworkExt>
@@ -63,7 +84,11 @@ where
     tx_pool:             <Ext::RethNode as NodeClientSpec>::TxPool,
     db_provider:         <Ext::RethNode as NodeClientSpec>::DbProvider,
     chain_spec:          Arc<<Ext::RethNode as NodeTypes>::ChainSpec>,
-    ipc_path_or_rpc_url: Option<String>
+    ipc_path_or_rpc_url: Option<String>,
+    forwarding_endpoint: Option<String>,
+    max_tasks:           usize,
+    /// shared with `trace` and `debug`, holds `max_tasks` permits
+    tracing_call_guard:  reth_tasks::pool::BlockingTaskGuard
 }
 
 impl<Ext: EthNetworkExt> RethNodeClient<Ext>
@@ -97,6 +122,20 @@ where
     pub fn eth_db_provider(&self) -> &<Ext::RethNode as NodeClientSpec>::DbProvider {
         &self.db_provider
     }
+
+    pub fn max_tasks(&self) -> usize {
+        self.max_tasks
+    }
+
+    pub(crate) fn tracing_call_guard(&self) -> reth_tasks::pool::BlockingTaskGuard {
+        self.tracing_call_guard.clone()
+    }
+
+    /// the sequencer (OP) or rpc/relay (L1) that accepted transactions are
+    /// forwarded to
+    pub fn forwarding_endpoint(&self) -> Option<&str> {
+        self.forwarding_endpoint.as_deref()
+    }
 }
 
 #[async_trait::async_trait]
@@ -110,32 +149,42 @@ where
             .as_ref()
             .ok_or_else(|| eyre::eyre!("no ipc path or rpc url has been set"))?;
 
-        let builder = builder::<Ext::AlloyNetwork>();
-
-        let client = if conn_url.ends_with(".ipc") {
-            builder
-                .connect_ipc(IpcConnect::new(conn_url.to_string()))
-                .await?
-        } else if conn_url.starts_with("ws:") || conn_url.contains("wss:") {
-            builder.connect_ws(WsConnect::new(conn_url)).await?
-        } else if conn_url.starts_with("http:") || conn_url.contains("https:") {
-            builder.connect_http(conn_url.parse()?)
-        } else {
-            builder.connect(conn_url).await?
-        };
-
-        Ok(client)
+        connect_provider(conn_url).await
     }
 }
 
+/// connects to an ipc path or a http/ws url
+pub(crate) async fn connect_provider<N: Network>(conn_url: &str) -> eyre::Result<RootProvider<N>> {
+    let builder = builder::<N>();
+
+    let client = if conn_url.ends_with(".ipc") {
+        builder
+            .connect_ipc(IpcConnect::new(conn_url.to_string()))
+            .await?
+    } else if conn_url.starts_with("ws:") || conn_url.contains("wss:") {
+        builder.connect_ws(WsConnect::new(conn_url)).await?
+    } else if conn_url.starts_with("http:") || conn_url.contains("https:") {
+        builder.connect_http(conn_url.parse()?)
+    } else {
+        builder.connect(conn_url).await?
+    };
+
+    Ok(client)
+}
+
 #[cfg(feature = "revm")]
 mod revm_impl {
 
+    use alloy_consensus::BlockHeader;
+    use alloy_eips::BlockId;
+    use reth_chainspec::EthChainSpec;
     use reth_provider::StateProviderFactory;
-    use reth_revm::database::StateProviderDatabase;
+    use revm::context::{BlockEnv, CfgEnv};
 
     use super::*;
-    use crate::traits::{EthRevm, EthRevmParams, reth_revm_utils::RethLibmdbxDatabaseRef};
+    use crate::traits::{
+        EthRevm, EthRevmParams, block_env_from_header, next_block_env, reth_revm_utils::RethLibmdbxDatabaseRef, spec_id_at
+    };
 
     impl<Ext> EthRevm for RethNodeClient<Ext>
     where
@@ -145,11 +194,63 @@ mod revm_impl {
         type InnerDb = RethLibmdbxDatabaseRef;
         type Params = EthRevmParams;
 
+        /// every concurrent reader of the returned database gets its own state
+        /// provider for the pinned block, up to one per available core
+        /// a `pending` block id reads the state of `latest`
         fn make_inner_db(&self, params: &EthRevmParams) -> eyre::Result<Self::InnerDb> {
-            let state_provider = self.eth_db_provider().state_by_block_id(params.block_id)?;
+            let block_id = if params.block_id.is_pending() { BlockId::latest() } else { params.block_id };
+            let pinned_block = self.pinned_block(block_id)?;
+
+            let db_provider = self.eth_db_provider().clone();
+            let open = move || -> eyre::Result<_> { Ok(db_provider.state_by_block_hash(pinned_block.hash)?) };
+
+            let max_providers = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+            Ok(RethLibmdbxDatabaseRef::with_opener(max_providers, open).with_pinned_block(pinned_block))
+        }
+
+        /// a `pending` block id is derived from `latest` with the chain's
+        /// block time and base fee params
+        fn make_block_env(&self, params: &EthRevmParams) -> eyre::Result<BlockEnv> {
+            let chain_spec = self.chain_spec();
+
+            if params.block_id.is_pending() {
+                let parent = self
+                    .eth_db_provider()
+                    .header_by_id(BlockId::latest())?
+                    .ok_or_else(|| eyre::eyre!("latest block not found"))?;
+                let timestamp = parent.timestamp() + Ext::block_time();
+
+                return Ok(next_block_env(
+                    &parent,
+                    Ext::block_time(),
+                    chain_spec.base_fee_params_at_timestamp(timestamp),
+                    chain_spec.blob_params_at_timestamp(timestamp)
+                ));
+            }
+
+            let header = self
+                .eth_db_provider()
+                .header_by_id(params.block_id)?
+                .ok_or_else(|| eyre::eyre!("block {} not found", params.block_id))?;
+
+            Ok(block_env_from_header(&header, chain_spec.blob_params_at_timestamp(header.timestamp())))
+        }
+
+        fn make_cfg_env(&self, params: &EthRevmParams, block_env: &BlockEnv) -> eyre::Result<CfgEnv> {
+            let spec = spec_id_at(
+                &*self.chain_spec(),
+                block_env.number.saturating_to(),
+                block_env.timestamp.saturating_to()
+            );
+
+            Ok(CfgEnv::new_with_spec(spec).with_chain_id(params.chain_id))
+        }
 
-            let this = StateProviderDatabase::new(state_provider);
-            Ok(RethLibmdbxDatabaseRef::new(this))
+        #[cfg(feature = "op-revm")]
+        fn make_op_spec(&self, _params: &EthRevmParams, block_env: &BlockEnv) -> eyre::Result<op_revm::OpSpecId> {
+            Ext::RethNode::op_spec_id_at(&self.chain_spec(), block_env.timestamp.saturating_to())
+                .ok_or_else(|| eyre::eyre!("chain {} is not an op stack chain", self.chain_spec().chain_id()))
         }
     }
 }
@@ -13,14 +13,14 @@ use reth_provider::{
 };
 use reth_rpc::{DebugApi, EthApi, EthFilter, TraceApi};
 use reth_rpc_eth_api::{RpcConverter, node::RpcNodeCoreAdapter};
-use reth_rpc_eth_types::{EthConfig, EthFilterConfig, receipt::EthReceiptConverter};
+use reth_rpc_eth_types::receipt::EthReceiptConverter;
 use reth_tasks::{Runtime, pool::BlockingTaskGuard};
 use reth_transaction_pool::{
-    CoinbaseTipOrdering, EthPooledTransaction, EthTransactionValidator, Pool, PoolConfig, TransactionValidationTaskExecutor,
+    CoinbaseTipOrdering, EthPooledTransaction, EthTransactionValidator, Pool, TransactionValidationTaskExecutor,
     blobstore::NoopBlobStore, validate::EthTransactionValidatorBuilder
 };
 
-use crate::reth_libmdbx::{DbConfig, NodeClientSpec, RethNodeClient};
+use crate::reth_libmdbx::{ApiConfig, DbConfig, NodeClientSpec, RethNodeClient};
 
 type RethApi = EthApi<
     RpcNodeCoreAdapter<RethDbProvider, RethTxPool, NoopNetwork, EthEvmConfig>,
@@ -46,10 +46,12 @@ impl NodeClientSpec for EthereumNode {
 
     fn new_with_db<Ext>(
         db_config: DbConfig,
+        api_config: ApiConfig,
         max_tasks: usize,
         task_executor: Runtime,
         chain_spec: Arc<Self::ChainSpec>,
-        ipc_path_or_rpc_url: Option<String>
+        ipc_path_or_rpc_url: Option<String>,
+        forwarding_endpoint: Option<String>
     ) -> eyre::Result<RethNodeClient<Ext>>
     where
         Ext: EthNetworkExt<RethNode = Self>
@@ -67,7 +69,7 @@ impl NodeClientSpec for EthereumNode {
             chain_spec.clone(),
             static_file_provider,
             rocksdb_provider,
-            super::provider_runtime()?
+            task_executor.clone()
         )?
         .with_read_only_sync(true);
 
@@ -79,17 +81,30 @@ impl NodeClientSpec for EthereumNode {
         let transaction_validator = EthTransactionValidatorBuilder::new(blockchain_provider.clone(), evm_config.clone())
             .build_with_tasks(task_executor.clone(), NoopBlobStore::default());
 
-        let tx_pool = Pool::eth_pool(transaction_validator, NoopBlobStore::default(), PoolConfig::default());
+        let tx_pool = Pool::eth_pool(transaction_validator, NoopBlobStore::default(), api_config.pool_config);
+
+        // there is no p2p network, so raw transactions reach the chain through
+        // the forwarding endpoint
+        let mut raw_tx_forwarder = api_config.eth_config.raw_tx_forwarder.clone();
+        if let Some(endpoint) = &forwarding_endpoint {
+            raw_tx_forwarder.tx_forwarder = Some(endpoint.parse()?);
+        }
 
         let api = EthApi::builder(blockchain_provider.clone(), tx_pool.clone(), NoopNetwork::default(), evm_config)
             .task_spawner(task_executor.clone())
+            .gas_cap(api_config.eth_config.rpc_gas_cap.into())
+            .max_simulate_blocks(api_config.eth_config.rpc_max_simulate_blocks)
+            .eth_proof_window(api_config.eth_config.eth_proof_window)
+            .eth_state_cache_config(api_config.eth_config.cache)
+            .gas_oracle_config(api_config.eth_config.gas_oracle.clone())
+            .raw_tx_forwarder(raw_tx_forwarder)
             .build();
 
         let tracing_call_guard = BlockingTaskGuard::new(max_tasks);
-        let trace = TraceApi::new(api.clone(), tracing_call_guard.clone(), EthConfig::default());
+        let trace = TraceApi::new(api.clone(), tracing_call_guard.clone(), api_config.eth_config);
 
-        let debug = DebugApi::new(api.clone(), tracing_call_guard, &task_executor, futures::stream::empty());
-        let filter = EthFilter::new(api.clone(), EthFilterConfig::default(), task_executor.clone());
+        let debug = DebugApi::new(api.clone(), tracing_call_guard.clone(), &task_executor, futures::stream::empty());
+        let filter = EthFilter::new(api.clone(), api_config.filter_config, task_executor.clone());
 
         Ok(RethNodeClient {
             api,
@@ -99,16 +114,23 @@ impl NodeClientSpec for EthereumNode {
             tx_pool,
             db_provider: blockchain_provider,
             chain_spec,
-            ipc_path_or_rpc_url
+            ipc_path_or_rpc_url,
+            forwarding_endpoint,
+            max_tasks,
+            tracing_call_guard
         })
     }
 }
 
 #[cfg(all(test, not(feature = "ci")))]
 mod tests {
-    use alloy_rpc_types::Filter;
+    use std::collections::HashSet;
+
+    use alloy_primitives::{B256, address};
+    use alloy_rpc_types::{BlockNumberOrTag, Filter, trace::filter::TraceFilter};
     use eth_network_exts::mainnet::MainnetExt;
     use reth_chainspec::MAINNET;
+    use reth_provider::BlockNumReader;
 
     use crate::{reth_libmdbx::RethNodeClientBuilder, test_utils::stream_timeout, traits::EthStream};
 
@@ -121,6 +143,16 @@ mod tests {
         assert!(builder.build().is_ok())
     }
 
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn can_build_with_api_config() {
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None)
+            .with_rpc_gas_cap(100_000_000)
+            .with_max_simulate_blocks(512)
+            .with_max_logs_per_response(100_000);
+        assert!(builder.build().is_ok())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     #[serial_test::serial]
     async fn test_block_stream() {
@@ -160,4 +192,206 @@ mod tests {
         let mempool_hash_stream = client.pending_transaction_hashes_stream().await.unwrap();
         assert!(stream_timeout(mempool_hash_stream, 2, 30).await.is_ok());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_block_state_diff() {
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        let block_number = client.eth_db_provider().best_block_number().unwrap() - 1;
+        let diff = client.block_state_diff(block_number, None).unwrap();
+        assert_eq!(diff.block_number, block_number);
+        assert!(!diff.accounts.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_account_history() {
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        let weth = address!("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let end_block = client.eth_db_provider().best_block_number().unwrap();
+        let changed_blocks = client.account_history(weth, end_block - 100..=end_block).unwrap();
+        assert!(!changed_blocks.is_empty());
+        assert!(changed_blocks.is_sorted());
+        assert!(changed_blocks.iter().all(|b| (end_block - 100..=end_block).contains(b)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_storage_slot_history() {
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        // the reserves of the uniswap v2 USDC/WETH pair, written by every swap
+        let pair = address!("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+        let reserves_slot = B256::with_last_byte(8);
+        let end_block = client.eth_db_provider().best_block_number().unwrap();
+        let changed_blocks = client
+            .storage_slot_history(pair, reserves_slot, end_block - 100..=end_block)
+            .unwrap();
+        assert!(!changed_blocks.is_empty());
+        assert!(changed_blocks.is_sorted());
+
+        let diff = client
+            .block_state_diff(changed_blocks[0], Some(&HashSet::from([pair])))
+            .unwrap();
+        assert!(
+            diff.accounts[0]
+                .storage
+                .iter()
+                .any(|slot| slot.slot == reserves_slot)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_trace_block_calls() {
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        let block_number = client.eth_db_provider().best_block_number().unwrap() - 1;
+        let frames = client
+            .trace_block_calls(BlockNumberOrTag::Number(block_number), false)
+            .await
+            .unwrap();
+        assert!(!frames.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_trace_filter_range() {
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        let end_block = client.eth_db_provider().best_block_number().unwrap();
+        let traces = client
+            .trace_filter_range(end_block - 9..=end_block, TraceFilter::default(), 2)
+            .await
+            .unwrap();
+        assert!(!traces.is_empty());
+        assert!(traces.is_sorted_by_key(|t| t.block_number));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_concurrent_revm_db() {
+        use alloy_eips::BlockId;
+        use revm::DatabaseRef;
+
+        use crate::traits::{EthRevm, EthRevmParams};
+
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        let weth = address!("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let db = client
+            .make_inner_db(&EthRevmParams { block_id: BlockId::latest(), chain_id: 1 })
+            .unwrap();
+
+        let balances = std::thread::scope(|s| {
+            let handles = (0..8)
+                .map(|_| s.spawn(|| db.basic_ref(weth).unwrap().unwrap().balance))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(balances.windows(2).all(|w| w[0] == w[1]));
+        assert!(db.state_provider().is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_single_state_provider_checked_back_in() {
+        use reth_provider::StateProviderFactory;
+        use reth_revm::database::StateProviderDatabase;
+
+        use crate::traits::reth_revm_utils::RethLibmdbxDatabaseRef;
+
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+        let db = RethLibmdbxDatabaseRef::new(StateProviderDatabase::new(client.eth_db_provider().latest().unwrap()));
+
+        let provider = db.state_provider().unwrap();
+        assert!(db.state_provider().is_err());
+        drop(provider);
+        assert!(db.state_provider().is_ok());
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            db.with_state_provider(|_| panic!("reader panicked")).unwrap()
+        }));
+        assert!(panicked.is_err());
+        assert!(db.state_provider().is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_state_providers_capped() {
+        use std::time::Duration;
+
+        use reth_provider::StateProviderFactory;
+
+        use crate::traits::reth_revm_utils::RethLibmdbxDatabaseRef;
+
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+        let db_provider = client.eth_db_provider().clone();
+        let db = RethLibmdbxDatabaseRef::with_opener(1, move || Ok(db_provider.latest()?));
+
+        let provider = db.state_provider().unwrap();
+        std::thread::scope(|s| {
+            let waiting = s.spawn(|| db.state_provider().map(drop));
+            std::thread::sleep(Duration::from_millis(100));
+            assert!(!waiting.is_finished());
+
+            drop(provider);
+            assert!(waiting.join().unwrap().is_ok());
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_make_block_env() {
+        use alloy_eips::BlockId;
+        use alloy_primitives::U256;
+        use revm::primitives::hardfork::SpecId;
+
+        use crate::traits::{EthRevm, EthRevmParams};
+
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        let latest = client
+            .make_block_env(&EthRevmParams { block_id: BlockId::latest(), chain_id: 1 })
+            .unwrap();
+        assert!(latest.basefee > 0);
+        assert!(latest.prevrandao.is_some());
+        assert!(latest.blob_excess_gas_and_price.is_some());
+
+        let params = EthRevmParams { block_id: BlockId::pending(), chain_id: 1 };
+        let pending = client.make_block_env(&params).unwrap();
+        assert_eq!(pending.number, latest.number + U256::from(1));
+        assert_eq!(pending.timestamp, latest.timestamp + U256::from(12));
+
+        let cfg = client.make_cfg_env(&params, &pending).unwrap();
+        assert!(cfg.spec >= SpecId::PRAGUE);
+        assert_eq!(cfg.chain_id, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_replay_block() {
+        let builder = RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None);
+        let client = builder.build().unwrap();
+
+        let block_number = client.eth_db_provider().best_block_number().unwrap() - 10;
+        let report = client.replay_block(block_number).unwrap();
+
+        assert!(report.transactions > 0);
+        assert!(report.is_consistent(), "{:#?}", report.mismatches);
+    }
 }
@@ -1,6 +1,5 @@
 use std::sync::Arc;
 
-use alloy_primitives::U256;
 use eth_network_exts::EthNetworkExt;
 use op_alloy_network::Optimism;
 use reth_db::{DatabaseEnv, open_db_read_only};
@@ -13,7 +12,7 @@ use reth_optimism_node::{
     txpool::{OpPooledTransaction, OpTransactionValidator}
 };
 use reth_optimism_rpc::{
-    OpEthApi,
+    OpEthApi, SequencerClient,
     eth::{receipt::OpReceiptConverter, transaction::OpTxInfoMapper}
 };
 use reth_provider::{
@@ -22,14 +21,13 @@ use reth_provider::{
 };
 use reth_rpc::{DebugApi, EthApi, EthFilter, TraceApi};
 use reth_rpc_eth_api::{RpcConverter, node::RpcNodeCoreAdapter};
-use reth_rpc_eth_types::{EthConfig, EthFilterConfig};
 use reth_tasks::{Runtime, pool::BlockingTaskGuard};
 use reth_transaction_pool::{
-    CoinbaseTipOrdering, Pool, PoolConfig, TransactionValidationTaskExecutor, blobstore::NoopBlobStore,
+    CoinbaseTipOrdering, Pool, TransactionValidationTaskExecutor, blobstore::NoopBlobStore,
     validate::EthTransactionValidatorBuilder
 };
 
-use crate::reth_libmdbx::{DbConfig, NodeClientSpec, RethNodeClient};
+use crate::reth_libmdbx::{ApiConfig, DbConfig, NodeClientSpec, RethNodeClient};
 
 type OpRethApi = OpEthApi<
     RpcNodeCoreAdapter<OpRethDbProvider, OpRethTxPool, NoopNetwork, OpEvmConfig>,
@@ -65,10 +63,12 @@ impl NodeClientSpec for OpNode {
 
     fn new_with_db<Ext>(
         db_config: DbConfig,
+        api_config: ApiConfig,
         max_tasks: usize,
         task_executor: Runtime,
         chain_spec: Arc<Self::ChainSpec>,
-        ipc_path_or_rpc_url: Option<String>
+        ipc_path_or_rpc_url: Option<String>,
+        forwarding_endpoint: Option<String>
     ) -> eyre::Result<RethNodeClient<Ext>>
     where
         Ext: EthNetworkExt<RethNode = Self>
@@ -86,7 +86,7 @@ impl NodeClientSpec for OpNode {
             chain_spec.clone(),
             static_file_provider,
             rocksdb_provider,
-            super::provider_runtime()?
+            task_executor.clone()
         )?
         .with_read_only_sync(true);
 
@@ -104,7 +104,7 @@ impl NodeClientSpec for OpNode {
             transaction_validator,
             CoinbaseTipOrdering::default(),
             NoopBlobStore::default(),
-            PoolConfig::default()
+            api_config.pool_config
         );
 
         let rpc_converter = RpcConverter::new(OpReceiptConverter::new(blockchain_provider.clone()))
@@ -114,15 +114,24 @@ impl NodeClientSpec for OpNode {
         let eth_api_inner =
             EthApi::builder(blockchain_provider.clone(), tx_pool.clone(), NoopNetwork::default(), evm_config)
                 .task_spawner(task_executor.clone())
+                .gas_cap(api_config.eth_config.rpc_gas_cap.into())
+                .max_simulate_blocks(api_config.eth_config.rpc_max_simulate_blocks)
+                .eth_proof_window(api_config.eth_config.eth_proof_window)
+                .eth_state_cache_config(api_config.eth_config.cache)
+                .gas_oracle_config(api_config.eth_config.gas_oracle.clone())
                 .with_rpc_converter(rpc_converter)
                 .build_inner();
-        let api = OpEthApi::new(eth_api_inner, None, U256::from(1_000_000u64), None);
+        let sequencer_client = forwarding_endpoint
+            .as_deref()
+            .map(sequencer_client)
+            .transpose()?;
+        let api = OpEthApi::new(eth_api_inner, sequencer_client, api_config.min_suggested_priority_fee, None);
 
         let tracing_call_guard = BlockingTaskGuard::new(max_tasks);
-        let trace = TraceApi::new(api.clone(), tracing_call_guard.clone(), EthConfig::default());
+        let trace = TraceApi::new(api.clone(), tracing_call_guard.clone(), api_config.eth_config);
 
-        let debug = DebugApi::new(api.clone(), tracing_call_guard, &task_executor, futures::stream::empty());
-        let filter = EthFilter::new(api.clone(), EthFilterConfig::default(), task_executor.clone());
+        let debug = DebugApi::new(api.clone(), tracing_call_guard.clone(), &task_executor, futures::stream::empty());
+        let filter = EthFilter::new(api.clone(), api_config.filter_config, task_executor.clone());
 
         Ok(RethNodeClient {
             api,
@@ -132,9 +141,28 @@ impl NodeClientSpec for OpNode {
             tx_pool,
             db_provider: blockchain_provider,
             chain_spec,
-            ipc_path_or_rpc_url
+            ipc_path_or_rpc_url,
+            forwarding_endpoint,
+            max_tasks,
+            tracing_call_guard
         })
     }
+
+    #[cfg(feature = "op-revm")]
+    fn op_spec_id_at(chain_spec: &OpChainSpec, timestamp: u64) -> Option<op_revm::OpSpecId> {
+        Some(reth_optimism_evm::revm_spec_by_timestamp_after_bedrock(chain_spec, timestamp))
+    }
+}
+
+/// the client `eth_sendRawTransaction` forwards to. only http endpoints are
+/// supported, connecting to one doesn't wait on any io so it can be done from
+/// the sync builder
+fn sequencer_client(endpoint: &str) -> eyre::Result<SequencerClient> {
+    if !(endpoint.starts_with("http:") || endpoint.starts_with("https:")) {
+        eyre::bail!("the sequencer endpoint must be an http url: {endpoint}");
+    }
+
+    Ok(futures::executor::block_on(SequencerClient::new(endpoint))?)
 }
 
 pub fn get_op_superchain_spec(str: &str) -> Arc<OpChainSpec> {
@@ -228,4 +256,50 @@ mod tests {
         let mempool_hash_stream = client.pending_transaction_hashes_stream().await.unwrap();
         assert!(stream_timeout(mempool_hash_stream, 2, 30).await.is_ok());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_send_raw_transaction_forwards_to_sequencer() {
+        use alloy_consensus::{SignableTransaction, TxEip1559, TxEnvelope};
+        use alloy_eips::eip2718::Encodable2718;
+        use alloy_primitives::{Address, Signature, TxKind, U256};
+
+        use crate::test_utils::mock_rpc_endpoint;
+
+        // r = 1 is on the curve, so some sender recovers from it
+        let tx = TxEnvelope::from(
+            TxEip1559 {
+                chain_id: 8453,
+                gas_limit: 21_000,
+                max_fee_per_gas: 1_000_000_000,
+                max_priority_fee_per_gas: 1,
+                to: TxKind::Call(Address::ZERO),
+                ..Default::default()
+            }
+            .into_signed(Signature::new(U256::ONE, U256::ONE, false))
+        );
+        let tx_hash = *tx.tx_hash();
+
+        let (sequencer, mut requests) = mock_rpc_endpoint(format!("\"{tx_hash}\"")).await.unwrap();
+        let client = RethNodeClientBuilder::<BaseMainnetExt>::new(
+            BASE_MAINNET_DB_PATH,
+            1000,
+            BASE_MAINNET.clone(),
+            Some(BASE_MAINNET_IPC_PATH),
+            None
+        )
+        .with_forwarding_endpoint(&sequencer)
+        .build()
+        .unwrap();
+
+        let hash = client
+            .submit_raw_transaction(tx.encoded_2718().into())
+            .await
+            .unwrap();
+        assert_eq!(hash, tx_hash);
+
+        let request = requests.recv().await.unwrap();
+        assert!(request.contains("eth_sendRawTransaction"));
+        assert!(request.contains(&alloy_primitives::hex::encode(tx.encoded_2718())));
+    }
 }
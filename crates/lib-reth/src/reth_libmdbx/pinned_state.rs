@@ -0,0 +1,130 @@
+use std::sync::{Arc, Mutex};
+
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_primitives::{Address, B256, BlockNumber, StorageKey, StorageValue};
+use eth_network_exts::EthNetworkExt;
+use reth_provider::{BlockIdReader, StateProvider, StateProviderBox, StateProviderFactory};
+
+use crate::reth_libmdbx::{NodeClientSpec, RethNodeClient};
+
+/// the block a [`BlockPinnedState`] was opened at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinnedBlock {
+    /// the id the state was requested with
+    pub block_id: BlockId,
+    pub number:   BlockNumber,
+    pub hash:     B256
+}
+
+impl PinnedBlock {
+    /// errors if `block_id` does not refer to this block. tags only match if the
+    /// state was opened with the same tag
+    pub fn ensure(&self, block_id: BlockId) -> eyre::Result<()> {
+        let matches = block_id == self.block_id
+            || match block_id {
+                BlockId::Hash(hash) => hash.block_hash == self.hash,
+                BlockId::Number(BlockNumberOrTag::Number(number)) => number == self.number,
+                BlockId::Number(_) => false
+            };
+
+        if !matches {
+            eyre::bail!(
+                "requested state for {block_id} but the state is pinned to block {} ({:?})",
+                self.number,
+                self.hash
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// one state provider for a single block. every read is checked against the
+/// pinned block so a caller can never silently get state for another block
+#[derive(Clone)]
+pub struct BlockPinnedState {
+    block: PinnedBlock,
+    state: Arc<Mutex<StateProviderBox>>
+}
+
+impl BlockPinnedState {
+    pub fn block(&self) -> PinnedBlock {
+        self.block
+    }
+
+    pub fn storage(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+        self.block.ensure(block_id)?;
+
+        let state = self.state.lock().map_err(|e| eyre::eyre!("{e}"))?;
+        Ok(state.storage(address, key)?.unwrap_or_default())
+    }
+}
+
+impl<Ext: EthNetworkExt> RethNodeClient<Ext>
+where
+    Ext::RethNode: NodeClientSpec
+{
+    /// resolves `block_id` once and opens the state at that block
+    pub fn state_at(&self, block_id: BlockId) -> eyre::Result<BlockPinnedState> {
+        let block = self.pinned_block(block_id)?;
+        let state = self.eth_db_provider().state_by_block_hash(block.hash)?;
+
+        Ok(BlockPinnedState { block, state: Arc::new(Mutex::new(state)) })
+    }
+
+    /// resolves `block_id` to the block it currently refers to
+    pub fn pinned_block(&self, block_id: BlockId) -> eyre::Result<PinnedBlock> {
+        let provider = self.eth_db_provider();
+
+        let hash = provider
+            .block_hash_for_id(block_id)?
+            .ok_or_else(|| eyre::eyre!("block {block_id} not found"))?;
+        let number = provider
+            .block_number_for_id(BlockId::hash(hash))?
+            .ok_or_else(|| eyre::eyre!("block {hash:?} not found"))?;
+
+        Ok(PinnedBlock { block_id, number, hash })
+    }
+}
+
+#[cfg(feature = "uniswap-storage")]
+mod _uniswap_storage {
+    use uniswap_storage::{StorageSlotFetcher, StorageSlotFetcherSync};
+
+    use super::*;
+
+    #[async_trait::async_trait]
+    impl StorageSlotFetcher for BlockPinnedState {
+        async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+            self.storage(address, key, block_id)
+        }
+    }
+
+    impl StorageSlotFetcherSync for BlockPinnedState {
+        fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+            self.storage(address, key, block_id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_eips::{BlockId, RpcBlockHash};
+    use alloy_primitives::B256;
+
+    use super::PinnedBlock;
+
+    #[test]
+    fn test_pinned_block_ensure() {
+        let hash = B256::repeat_byte(1);
+        let pinned = PinnedBlock { block_id: BlockId::latest(), number: 100, hash };
+
+        assert!(pinned.ensure(BlockId::latest()).is_ok());
+        assert!(pinned.ensure(BlockId::number(100)).is_ok());
+        assert!(pinned.ensure(BlockId::Hash(RpcBlockHash::from_hash(hash, None))).is_ok());
+
+        assert!(pinned.ensure(BlockId::number(99)).is_err());
+        assert!(pinned.ensure(BlockId::pending()).is_err());
+        assert!(pinned.ensure(BlockId::Hash(RpcBlockHash::from_hash(B256::ZERO, None))).is_err());
+    }
+}
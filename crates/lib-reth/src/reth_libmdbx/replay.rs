@@ -0,0 +1,226 @@
+use alloy_consensus::{BlockHeader, TxReceipt};
+use alloy_eips::{BlockId, eip2718::Encodable2718};
+use alloy_primitives::{BlockNumber, Log, TxHash};
+use alloy_rpc_types::TransactionRequest;
+use eth_network_exts::EthNetworkExt;
+use reth_chainspec::EthChainSpec;
+use reth_evm::{eth::EthEvm, system_calls::SystemCaller};
+use reth_node_ethereum::EthereumNode;
+use reth_provider::{BlockReader, ReceiptProvider, TransactionVariant};
+use revm::{
+    Context, DatabaseCommit, DatabaseRef, MainBuilder, MainContext,
+    context_interface::ContextTr,
+    handler::EvmTr,
+    state::EvmState
+};
+use revm_database::CacheDB;
+
+use crate::{
+    reth_libmdbx::RethNodeClient,
+    traits::{EthRevm, EthRevmParams, SimulationOutcome, block_env_from_header, execute_mainnet_evm}
+};
+
+/// what a transaction's receipt says, or what replaying it produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayedReceipt {
+    pub success:  bool,
+    pub gas_used: u64,
+    pub logs:     Vec<Log>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptMismatch {
+    pub index:    usize,
+    pub tx_hash:  TxHash,
+    /// from the receipt in the database
+    pub expected: ReplayedReceipt,
+    /// from replaying the transaction
+    pub actual:   ReplayedReceipt
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockReplayReport {
+    pub block_number: BlockNumber,
+    pub transactions: usize,
+    pub mismatches:   Vec<ReceiptMismatch>
+}
+
+impl BlockReplayReport {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl<Ext> RethNodeClient<Ext>
+where
+    Ext: EthNetworkExt<RethNode = EthereumNode>
+{
+    /// re-executes every transaction of `block_number` with the mainnet evm on
+    /// top of its parent's state and compares the results with the stored
+    /// receipts. the pre-block system calls (eip-4788 beacon root, eip-2935
+    /// history storage) are applied first, as the block executor does. op
+    /// nodes replay with [`RethNodeClient::replay_op_block`]
+    pub fn replay_block(&self, block_number: BlockNumber) -> eyre::Result<BlockReplayReport> {
+        let provider = self.eth_db_provider();
+        let block = provider
+            .recovered_block(block_number.into(), TransactionVariant::WithHash)?
+            .ok_or_else(|| eyre::eyre!("block {block_number} not found"))?;
+        let receipts = provider
+            .receipts_by_block(block_number.into())?
+            .ok_or_else(|| eyre::eyre!("receipts of block {block_number} not found"))?;
+
+        let parent = parent_params::<Ext>(block_number)?;
+        let block_env = block_env_from_header(
+            block.header(),
+            self.chain_spec()
+                .blob_params_at_timestamp(block.header().timestamp())
+        );
+        let cfg_env = self.make_cfg_env(&parent, &block_env)?;
+
+        let evm = Context::mainnet()
+            .with_block(block_env)
+            .with_cfg(cfg_env)
+            .with_db(self.make_cache_db(&parent)?)
+            .build_mainnet();
+
+        let mut system_evm = EthEvm::new(evm, false);
+        SystemCaller::new(&*self.chain_spec())
+            .apply_pre_execution_changes(block.header(), &mut system_evm)
+            .map_err(|e| eyre::eyre!("{e}"))?;
+        let mut evm = system_evm.into_inner();
+
+        let txs = block
+            .transactions_with_sender()
+            .map(|(sender, tx)| (tx.trie_hash(), TransactionRequest::from_transaction_with_sender(tx.clone(), *sender)))
+            .collect();
+
+        replay_transactions(block_number, txs, &receipts, &mut evm, execute_mainnet_evm)
+    }
+}
+
+/// the state a block is replayed on
+fn parent_params<Ext: EthNetworkExt>(block_number: BlockNumber) -> eyre::Result<EthRevmParams> {
+    if block_number == 0 {
+        eyre::bail!("the genesis block has no transactions to replay");
+    }
+
+    Ok(EthRevmParams { block_id: BlockId::number(block_number - 1), chain_id: Ext::CHAIN_ID })
+}
+
+/// executes `txs` in order, committing each one, and compares the results
+/// with `receipts`
+fn replay_transactions<E, DB, T, R>(
+    block_number: BlockNumber,
+    txs: Vec<(TxHash, T)>,
+    receipts: &[R],
+    evm: &mut E,
+    mut execute: impl FnMut(&mut E, T) -> eyre::Result<(SimulationOutcome, EvmState)>
+) -> eyre::Result<BlockReplayReport>
+where
+    E: EvmTr<Context: ContextTr<Db = CacheDB<DB>>>,
+    DB: DatabaseRef,
+    R: TxReceipt<Log = Log>
+{
+    if txs.len() != receipts.len() {
+        eyre::bail!("block {block_number} has {} transactions but {} receipts", txs.len(), receipts.len());
+    }
+
+    let mut report = BlockReplayReport { block_number, transactions: txs.len(), mismatches: Vec::new() };
+    let mut cumulative_gas_used = 0;
+
+    for (index, ((tx_hash, tx), receipt)) in txs.into_iter().zip(receipts).enumerate() {
+        let expected = ReplayedReceipt {
+            success:  receipt.status(),
+            gas_used: receipt.cumulative_gas_used() - cumulative_gas_used,
+            logs:     receipt.logs().to_vec()
+        };
+        cumulative_gas_used = receipt.cumulative_gas_used();
+
+        let (outcome, state) = execute(evm, tx)?;
+        evm.ctx_mut().db_mut().commit(state);
+
+        let actual = ReplayedReceipt { success: outcome.is_success(), gas_used: outcome.gas_used, logs: outcome.logs };
+        if actual != expected {
+            report
+                .mismatches
+                .push(ReceiptMismatch { index, tx_hash, expected, actual });
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(feature = "op-reth-db")]
+mod op_impl {
+    use alloy_primitives::Bytes;
+    use op_alloy_consensus::OpTxEnvelope;
+    use op_revm::{DefaultOp, OpBuilder, transaction::deposit::DepositTransactionParts};
+    use reth_optimism_evm::{OpEvm, revm_spec_by_timestamp_after_bedrock};
+    use reth_optimism_node::OpNode;
+
+    use super::*;
+    use crate::traits::execute_op_evm;
+
+    impl<Ext> RethNodeClient<Ext>
+    where
+        Ext: EthNetworkExt<RethNode = OpNode>
+    {
+        /// [`RethNodeClient::replay_block`] with the op evm, deposits and the
+        /// l1 data fee of the signed transactions included. the create2
+        /// deployer canyon force-deploys is not, so only its activation block
+        /// can diverge
+        pub fn replay_op_block(&self, block_number: BlockNumber) -> eyre::Result<BlockReplayReport> {
+            let provider = self.eth_db_provider();
+            let block = provider
+                .recovered_block(block_number.into(), TransactionVariant::WithHash)?
+                .ok_or_else(|| eyre::eyre!("block {block_number} not found"))?;
+            let receipts = provider
+                .receipts_by_block(block_number.into())?
+                .ok_or_else(|| eyre::eyre!("receipts of block {block_number} not found"))?;
+
+            let parent = parent_params::<Ext>(block_number)?;
+            let block_env = block_env_from_header(
+                block.header(),
+                self.chain_spec()
+                    .blob_params_at_timestamp(block.header().timestamp())
+            );
+            let spec = revm_spec_by_timestamp_after_bedrock(&*self.chain_spec(), block.header().timestamp());
+
+            let evm = Context::op()
+                .with_block(block_env)
+                .modify_cfg_chained(|cfg| {
+                    cfg.chain_id = Ext::CHAIN_ID;
+                    cfg.spec = spec;
+                })
+                .with_db(self.make_cache_db(&parent)?)
+                .build_op();
+
+            let mut system_evm = OpEvm::new(evm, false);
+            SystemCaller::new(&*self.chain_spec())
+                .apply_pre_execution_changes(block.header(), &mut system_evm)
+                .map_err(|e| eyre::eyre!("{e}"))?;
+            let mut evm = system_evm.into_inner();
+
+            let txs = block
+                .transactions_with_sender()
+                .map(|(sender, tx)| {
+                    let deposit = match tx {
+                        OpTxEnvelope::Deposit(deposit) => Some(DepositTransactionParts {
+                            source_hash:           deposit.source_hash,
+                            mint:                  Some(deposit.mint),
+                            is_system_transaction: deposit.is_system_transaction
+                        }),
+                        _ => None
+                    };
+                    let request = TransactionRequest::from_transaction_with_sender(tx.clone(), *sender);
+
+                    (tx.trie_hash(), (request, deposit, Bytes::from(tx.encoded_2718())))
+                })
+                .collect();
+
+            replay_transactions(block_number, txs, &receipts, &mut evm, |evm, (tx, deposit, enveloped_tx)| {
+                execute_op_evm(evm, tx, deposit, Some(enveloped_tx))
+            })
+        }
+    }
+}
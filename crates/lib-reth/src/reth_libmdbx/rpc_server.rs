@@ -0,0 +1,261 @@
+use std::net::SocketAddr;
+
+use eth_network_exts::EthNetworkExt;
+use jsonrpsee::{
+    RpcModule,
+    server::{Server, ServerHandle}
+};
+use reth_rpc_api::{DebugApiServer, TraceApiServer};
+use reth_rpc_eth_api::{EthApiServer, EthFilterApiServer};
+
+use crate::reth_libmdbx::{NodeClientSpec, RethNodeClient};
+
+/// where to serve a [`RethNodeClient`] and which methods to expose
+#[derive(Debug, Clone, Default)]
+pub struct RpcServerConfig {
+    /// serves both HTTP and WS
+    pub http_addr:       Option<SocketAddr>,
+    pub ipc_path:        Option<String>,
+    /// exact method names (`eth_call`) or namespace wildcards (`trace_*`).
+    /// `None` serves [`READ_ONLY_METHODS`]
+    pub allowed_methods: Option<Vec<String>>
+}
+
+impl RpcServerConfig {
+    pub fn with_http(mut self, http_addr: SocketAddr) -> Self {
+        self.http_addr = Some(http_addr);
+        self
+    }
+
+    pub fn with_ipc(mut self, ipc_path: &str) -> Self {
+        self.ipc_path = Some(ipc_path.to_string());
+        self
+    }
+
+    pub fn with_allowed_methods<S: ToString>(mut self, allowed_methods: impl IntoIterator<Item = S>) -> Self {
+        self.allowed_methods = Some(allowed_methods.into_iter().map(|m| m.to_string()).collect());
+        self
+    }
+
+    fn is_allowed(&self, method: &str) -> bool {
+        match &self.allowed_methods {
+            Some(allowed) => allowed.iter().any(|a| match a.strip_suffix('*') {
+                Some(prefix) => method.starts_with(prefix),
+                None => method == a
+            }),
+            None => READ_ONLY_METHODS.contains(&method)
+        }
+    }
+}
+
+/// the `eth`, `trace` and `debug` methods served when no methods are set.
+/// anything that sends, signs, mines or writes to disk is left out, as is
+/// any method added upstream until it is listed here
+pub const READ_ONLY_METHODS: &[&str] = &[
+    // eth
+    "eth_protocolVersion",
+    "eth_syncing",
+    "eth_chainId",
+    "eth_blockNumber",
+    "eth_getBlockByHash",
+    "eth_getBlockByNumber",
+    "eth_getBlockTransactionCountByHash",
+    "eth_getBlockTransactionCountByNumber",
+    "eth_getBlockReceipts",
+    "eth_getUncleCountByBlockHash",
+    "eth_getUncleCountByBlockNumber",
+    "eth_getUncleByBlockHashAndIndex",
+    "eth_getUncleByBlockNumberAndIndex",
+    "eth_getHeaderByHash",
+    "eth_getHeaderByNumber",
+    "eth_getTransactionByHash",
+    "eth_getRawTransactionByHash",
+    "eth_getTransactionByBlockHashAndIndex",
+    "eth_getRawTransactionByBlockHashAndIndex",
+    "eth_getTransactionByBlockNumberAndIndex",
+    "eth_getRawTransactionByBlockNumberAndIndex",
+    "eth_getTransactionBySenderAndNonce",
+    "eth_getTransactionReceipt",
+    "eth_getBalance",
+    "eth_getStorageAt",
+    "eth_getTransactionCount",
+    "eth_getCode",
+    "eth_getAccount",
+    "eth_getAccountInfo",
+    "eth_getProof",
+    "eth_call",
+    "eth_callMany",
+    "eth_simulateV1",
+    "eth_createAccessList",
+    "eth_estimateGas",
+    "eth_gasPrice",
+    "eth_maxPriorityFeePerGas",
+    "eth_blobBaseFee",
+    "eth_feeHistory",
+    "eth_getLogs",
+    "eth_newFilter",
+    "eth_newBlockFilter",
+    "eth_newPendingTransactionFilter",
+    "eth_getFilterChanges",
+    "eth_getFilterLogs",
+    "eth_uninstallFilter",
+    // trace
+    "trace_call",
+    "trace_callMany",
+    "trace_rawTransaction",
+    "trace_replayBlockTransactions",
+    "trace_replayTransaction",
+    "trace_block",
+    "trace_filter",
+    "trace_get",
+    "trace_transaction",
+    "trace_transactionOpcodeGas",
+    "trace_blockOpcodeGas",
+    // debug
+    "debug_getRawHeader",
+    "debug_getRawBlock",
+    "debug_getRawTransaction",
+    "debug_getRawTransactions",
+    "debug_getRawReceipts",
+    "debug_getBadBlocks",
+    "debug_traceChain",
+    "debug_traceBlock",
+    "debug_traceBlockByHash",
+    "debug_traceBlockByNumber",
+    "debug_traceTransaction",
+    "debug_traceCall",
+    "debug_traceCallMany",
+    "debug_executionWitness",
+    "debug_executionWitnessByBlockHash",
+    "debug_getModifiedAccountsByNumber",
+    "debug_getModifiedAccountsByHash",
+    "debug_intermediateRoots",
+    "debug_storageRangeAt",
+    "debug_accountRange"
+];
+
+/// keeps the servers running until [`RpcServerHandle::stop`] is called or it
+/// is dropped
+pub struct RpcServerHandle {
+    http_addr: Option<SocketAddr>,
+    http:      Option<ServerHandle>,
+    ipc:       Option<reth_ipc::server::ServerHandle>
+}
+
+impl RpcServerHandle {
+    pub fn http_addr(&self) -> Option<SocketAddr> {
+        self.http_addr
+    }
+
+    pub fn stop(self) -> eyre::Result<()> {
+        if let Some(http) = self.http {
+            http.stop()?;
+        }
+        if let Some(ipc) = self.ipc {
+            ipc.stop()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<Ext: EthNetworkExt> RethNodeClient<Ext>
+where
+    Ext::RethNode: NodeClientSpec
+{
+    /// the client's `eth`, `trace` and `debug` apis as one module, reduced to
+    /// the methods allowed by `config`
+    pub fn rpc_module(&self, config: &RpcServerConfig) -> eyre::Result<RpcModule<()>> {
+        let mut module = RpcModule::new(());
+        module.merge(self.eth_api().into_rpc())?;
+        module.merge(self.eth_filter().into_rpc())?;
+        module.merge(self.eth_trace().into_rpc())?;
+        module.merge(self.eth_debug().into_rpc())?;
+
+        let disallowed = module
+            .method_names()
+            .filter(|method| !config.is_allowed(method))
+            .collect::<Vec<_>>();
+        for method in disallowed {
+            module.remove_method(method);
+        }
+
+        Ok(module)
+    }
+
+    /// serves the database read-only over the endpoints set in `config`
+    pub async fn start_rpc_server(&self, config: RpcServerConfig) -> eyre::Result<RpcServerHandle> {
+        if config.http_addr.is_none() && config.ipc_path.is_none() {
+            eyre::bail!("no http address or ipc path has been set");
+        }
+
+        let module = self.rpc_module(&config)?;
+
+        let (http_addr, http) = match config.http_addr {
+            Some(addr) => {
+                let server = Server::builder().build(addr).await?;
+                (Some(server.local_addr()?), Some(server.start(module.clone())))
+            }
+            None => (None, None)
+        };
+
+        let ipc = match config.ipc_path {
+            Some(ipc_path) => Some(
+                reth_ipc::server::Builder::default()
+                    .build(ipc_path)
+                    .start(module)
+                    .await?
+            ),
+            None => None
+        };
+
+        Ok(RpcServerHandle { http_addr, http, ipc })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{READ_ONLY_METHODS, RpcServerConfig};
+
+    #[test]
+    fn test_allowed_methods() {
+        let config = RpcServerConfig::default();
+        assert!(config.is_allowed("eth_call"));
+        assert!(config.is_allowed("debug_traceTransaction"));
+        assert!(!config.is_allowed("eth_sendRawTransaction"));
+        assert!(!config.is_allowed("eth_sendRawTransactionSync"));
+        assert!(!config.is_allowed("eth_signTransaction"));
+        assert!(!config.is_allowed("eth_submitWork"));
+        assert!(!config.is_allowed("debug_standardTraceBlockToFile"));
+        assert!(!config.is_allowed("txpool_content"));
+
+        let config = config.with_allowed_methods(["eth_call", "trace_*"]);
+        assert!(config.is_allowed("eth_call"));
+        assert!(config.is_allowed("trace_filter"));
+        assert!(!config.is_allowed("eth_callMany"));
+        assert!(!config.is_allowed("debug_traceTransaction"));
+
+        let config = RpcServerConfig::default().with_allowed_methods(["eth_*"]);
+        assert!(config.is_allowed("eth_sendRawTransaction"));
+    }
+
+    #[cfg(not(feature = "ci"))]
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_rpc_module_is_read_only() {
+        let client = crate::test_utils::mainnet_client();
+        let module = client
+            .rpc_module(&RpcServerConfig::default())
+            .unwrap();
+        let methods = module.method_names().collect::<Vec<_>>();
+        assert!(methods.contains(&"eth_call"));
+        assert!(
+            methods.iter().all(|method| READ_ONLY_METHODS.contains(method)),
+            "{:?}",
+            methods
+                .iter()
+                .filter(|method| !READ_ONLY_METHODS.contains(method))
+                .collect::<Vec<_>>()
+        );
+    }
+}
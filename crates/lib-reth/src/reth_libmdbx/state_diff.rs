@@ -0,0 +1,121 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::RangeInclusive
+};
+
+use alloy_primitives::{Address, B256, BlockNumber, U256};
+use eth_network_exts::EthNetworkExt;
+use futures::{Stream, StreamExt};
+use reth_provider::{ChangeSetReader, DatabaseProviderFactory, StateProvider, StateProviderFactory, StorageChangeSetReader};
+
+use crate::reth_libmdbx::{NodeClientSpec, RethNodeClient};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountState {
+    pub nonce:         u64,
+    pub balance:       U256,
+    pub bytecode_hash: Option<B256>
+}
+
+/// `None` on either side means the account did not exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountInfoDiff {
+    pub before: Option<AccountState>,
+    pub after:  Option<AccountState>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageSlotDiff {
+    pub slot:   B256,
+    pub before: U256,
+    pub after:  U256
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub address: Address,
+    /// `None` if only the account's storage changed
+    pub info:    Option<AccountInfoDiff>,
+    pub storage: Vec<StorageSlotDiff>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockStateDiff {
+    pub block_number: BlockNumber,
+    pub accounts:     Vec<AccountDiff>
+}
+
+impl<Ext: EthNetworkExt> RethNodeClient<Ext>
+where
+    Ext::RethNode: NodeClientSpec
+{
+    /// reads the account and storage changesets of `block_number`. if
+    /// `addresses` is set, only changes to those contracts are returned
+    pub fn block_state_diff(
+        &self,
+        block_number: BlockNumber,
+        addresses: Option<&HashSet<Address>>
+    ) -> eyre::Result<BlockStateDiff> {
+        let include = |address: &Address| addresses.is_none_or(|a| a.contains(address));
+
+        let provider = self.eth_db_provider().database_provider_ro()?;
+        let account_changes = provider.account_block_changeset(block_number)?;
+        let storage_changes = provider.storage_changeset(block_number)?;
+        drop(provider);
+
+        // the changesets only hold the pre-block values, the post-block values are
+        // read from the state at the end of the block
+        let state = self.eth_db_provider().history_by_block_number(block_number)?;
+
+        let mut accounts: BTreeMap<Address, AccountDiff> = BTreeMap::new();
+
+        for change in account_changes
+            .into_iter()
+            .filter(|c| include(&c.address))
+        {
+            let after = state.basic_account(&change.address)?;
+            let info = AccountInfoDiff {
+                before: change.info.map(|a| AccountState {
+                    nonce:         a.nonce,
+                    balance:       a.balance,
+                    bytecode_hash: a.bytecode_hash
+                }),
+                after:  after.map(|a| AccountState {
+                    nonce:         a.nonce,
+                    balance:       a.balance,
+                    bytecode_hash: a.bytecode_hash
+                })
+            };
+
+            accounts
+                .entry(change.address)
+                .or_insert_with(|| AccountDiff { address: change.address, info: None, storage: Vec::new() })
+                .info = Some(info);
+        }
+
+        for (block_address, entry) in storage_changes {
+            let address = block_address.address();
+            if !include(&address) {
+                continue;
+            }
+
+            let after = state.storage(address, entry.key)?.unwrap_or_default();
+            accounts
+                .entry(address)
+                .or_insert_with(|| AccountDiff { address, info: None, storage: Vec::new() })
+                .storage
+                .push(StorageSlotDiff { slot: entry.key, before: entry.value, after });
+        }
+
+        Ok(BlockStateDiff { block_number, accounts: accounts.into_values().collect() })
+    }
+
+    /// streams [`BlockStateDiff`]s for every block in `range`, in order
+    pub fn state_diff_stream(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+        addresses: Option<HashSet<Address>>
+    ) -> impl Stream<Item = eyre::Result<BlockStateDiff>> + '_ {
+        futures::stream::iter(range).map(move |block_number| self.block_state_diff(block_number, addresses.as_ref()))
+    }
+}
@@ -0,0 +1,23 @@
+use alloy_primitives::{Bytes, TxHash};
+use eth_network_exts::EthNetworkExt;
+use reth_rpc_eth_api::helpers::EthTransactions;
+
+use crate::reth_libmdbx::{NodeClientSpec, RethNodeClient};
+
+impl<Ext: EthNetworkExt> RethNodeClient<Ext>
+where
+    Ext::RethNode: NodeClientSpec
+{
+    /// `eth_sendRawTransaction` on the in-process eth api, which hands `tx`
+    /// to the forwarding endpoint over the client it connected once when the
+    /// node client was built: the sequencer client for OP chains, the raw
+    /// transaction forwarder for L1. a rejection from the endpoint or the
+    /// local pool is returned as an error
+    pub async fn submit_raw_transaction(&self, tx: Bytes) -> eyre::Result<TxHash> {
+        if self.forwarding_endpoint().is_none() {
+            eyre::bail!("no forwarding endpoint has been set");
+        }
+
+        Ok(EthTransactions::send_raw_transaction(&self.eth_api(), tx).await?)
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::RangeInclusive;
+
+use alloy_primitives::{B256, BlockNumber};
+use alloy_rpc_types::{
+    BlockNumberOrTag,
+    trace::{
+        filter::TraceFilter,
+        geth::{
+            CallConfig, CallFrame, DiffMode, GethDebugTracingOptions, PreStateConfig, PreStateFrame, TraceResult
+        },
+        parity::LocalizedTransactionTrace
+    }
+};
+use eth_network_exts::EthNetworkExt;
+use futures::{StreamExt, TryStreamExt};
+use reth_rpc_api::{DebugApiServer, TraceApiServer};
+
+use crate::reth_libmdbx::{NodeClientSpec, RethNodeClient};
+
+/// in-process `debug_*` and `trace_*` calls. every call goes through the
+/// client's `BlockingTaskGuard`, so at most `max_tasks` traces run at once
+impl<Ext: EthNetworkExt> RethNodeClient<Ext>
+where
+    Ext::RethNode: NodeClientSpec
+{
+    /// `debug_traceTransaction` with the `callTracer`
+    pub async fn call_trace_transaction(&self, tx_hash: B256, only_top_call: bool) -> eyre::Result<CallFrame> {
+        let opts = GethDebugTracingOptions::call_tracer(call_config(only_top_call));
+
+        Ok(DebugApiServer::debug_trace_transaction(&self.eth_debug(), tx_hash, Some(opts))
+            .await?
+            .try_into_call_frame()?)
+    }
+
+    /// `debug_traceTransaction` with the `prestateTracer` in diff mode
+    pub async fn prestate_diff_transaction(&self, tx_hash: B256) -> eyre::Result<DiffMode> {
+        let opts = GethDebugTracingOptions::prestate_tracer(PreStateConfig {
+            diff_mode: Some(true),
+            ..Default::default()
+        });
+
+        match DebugApiServer::debug_trace_transaction(&self.eth_debug(), tx_hash, Some(opts))
+            .await?
+            .try_into_pre_state_frame()?
+        {
+            PreStateFrame::Diff(diff) => Ok(diff),
+            PreStateFrame::Default(_) => eyre::bail!("prestate tracer did not return a diff for {tx_hash:?}")
+        }
+    }
+
+    /// `debug_traceBlockByNumber` with the `callTracer`, one frame per
+    /// transaction in block order
+    pub async fn trace_block_calls(&self, block: BlockNumberOrTag, only_top_call: bool) -> eyre::Result<Vec<CallFrame>> {
+        let opts = GethDebugTracingOptions::call_tracer(call_config(only_top_call));
+
+        DebugApiServer::debug_trace_block_by_number(&self.eth_debug(), block, Some(opts))
+            .await?
+            .into_iter()
+            .map(|res| match res {
+                TraceResult::Success { result, .. } => Ok(result.try_into_call_frame()?),
+                TraceResult::Error { error, tx_hash } => eyre::bail!("failed to trace {tx_hash:?}: {error}")
+            })
+            .collect()
+    }
+
+    /// `trace_filter`
+    pub async fn trace_filter(&self, filter: TraceFilter) -> eyre::Result<Vec<LocalizedTransactionTrace>> {
+        Ok(TraceApiServer::trace_filter(&self.eth_trace(), filter).await?)
+    }
+
+    /// `trace_filter` over `range`, split into chunks of `chunk_size` blocks
+    /// that are traced in parallel. the block bounds of `filter` are ignored
+    /// and the traces are returned in block order. every chunk holds a permit
+    /// of the client's `BlockingTaskGuard` while it is traced, and at most
+    /// half of the permits are taken at once so a trace that takes its own
+    /// permit can always get one
+    pub async fn trace_filter_range(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+        filter: TraceFilter,
+        chunk_size: u64
+    ) -> eyre::Result<Vec<LocalizedTransactionTrace>> {
+        let end = *range.end();
+        let chunks = range
+            .step_by(chunk_size.max(1) as usize)
+            .map(|start| (start, (start + chunk_size.max(1) - 1).min(end)));
+
+        let guard = self.tracing_call_guard();
+        futures::stream::iter(chunks)
+            .map(|(from_block, to_block)| {
+                let filter = TraceFilter { from_block: Some(from_block), to_block: Some(to_block), ..filter.clone() };
+                let guard = guard.clone();
+                async move {
+                    let _permit = guard
+                        .acquire_owned()
+                        .await
+                        .map_err(|e| eyre::eyre!("{e}"))?;
+                    self.trace_filter(filter).await
+                }
+            })
+            .buffered((self.max_tasks() / 2).max(1))
+            .try_concat()
+            .await
+    }
+}
+
+fn call_config(only_top_call: bool) -> CallConfig {
+    CallConfig { only_top_call: Some(only_top_call), with_log: Some(true) }
+}
@@ -28,6 +28,10 @@ where
     P: Provider<N> + Clone,
     N: Network
 {
+    pub fn new(provider: P) -> Self {
+        Self { provider, _phantom: PhantomData }
+    }
+
     pub fn provider(&self) -> &P {
         &self.provider
     }
@@ -100,24 +104,265 @@ where
     }
 }
 
+#[cfg(feature = "revm")]
+pub use revm_impl::RpcDatabaseRef;
+
 #[cfg(feature = "revm")]
 mod revm_impl {
 
+    use std::{future::Future, sync::Arc};
+
+    use alloy_consensus::BlockHeader;
+    use alloy_eips::{BlockId, eip7840::BlobParams};
+    use alloy_network::BlockResponse;
+    use alloy_primitives::{Address, B256, ChainId, U256};
+    use alloy_rpc_types::{TransactionRequest, state::EvmOverrides};
+    use reth_chainspec::{ChainSpec, EthChainSpec, HOLESKY, HOODI, MAINNET, SEPOLIA};
+    use revm::{
+        DatabaseRef,
+        context::{BlockEnv, CfgEnv},
+        context_interface::ContextTr,
+        handler::EvmTr,
+        state::{AccountInfo, Bytecode}
+    };
     use revm_database::{AlloyDB, WrapDatabaseAsync};
+    use tokio::runtime::RuntimeFlavor;
 
     use super::*;
-    use crate::traits::{AsyncEthRevmParams, EthRevm};
+    use crate::traits::{
+        AsyncEthRevmParams, EthRevm, MainnetRevmEvm, PrefetchConfig, SimulationOutcome, block_env_from_header,
+        prefetch_state, simulate_mainnet_evm, spec_id_at
+    };
+
+    /// the chain spec of `chain_id` if it is a known ethereum chain
+    fn known_chain_spec(chain_id: ChainId) -> Option<Arc<ChainSpec>> {
+        [&MAINNET, &SEPOLIA, &HOLESKY, &HOODI]
+            .into_iter()
+            .find(|chain_spec| chain_spec.chain_id() == chain_id)
+            .map(|chain_spec| Arc::clone(&**chain_spec))
+    }
+
+    /// the blob params of `header`'s fork. on unknown chains the fork is told
+    /// from the header fields, which can't tell osaka and the bpo forks from
+    /// prague
+    fn header_blob_params<H: BlockHeader>(chain_id: ChainId, header: &H) -> Option<BlobParams> {
+        match known_chain_spec(chain_id) {
+            Some(chain_spec) => chain_spec.blob_params_at_timestamp(header.timestamp()),
+            None if header.requests_hash().is_some() => Some(BlobParams::prague()),
+            None => header.excess_blob_gas().map(|_| BlobParams::cancun())
+        }
+    }
+
+    /// the block env of `params.block_id`, `pending` included, as served by the
+    /// node
+    fn rpc_block_env<P, N>(provider: &P, params: &AsyncEthRevmParams) -> eyre::Result<BlockEnv>
+    where
+        P: Provider<N>,
+        N: Network
+    {
+        let block = block_on(&params.handle, async { provider.get_block(params.block_id).await })?
+            .ok_or_else(|| eyre::eyre!("block {} not found", params.block_id))?;
+        let blob_params = header_blob_params(params.chain_id, block.header());
+
+        Ok(block_env_from_header(block.header(), blob_params))
+    }
+
+    /// the cfg env of `block_env` on a known chain, the latest spec otherwise
+    fn rpc_cfg_env(params: &AsyncEthRevmParams, block_env: &BlockEnv) -> CfgEnv {
+        let spec = known_chain_spec(params.chain_id)
+            .map(|chain_spec| {
+                spec_id_at(&*chain_spec, block_env.number.saturating_to(), block_env.timestamp.saturating_to())
+            })
+            .unwrap_or_default();
+
+        CfgEnv::new_with_spec(spec).with_chain_id(params.chain_id)
+    }
+
+    /// runs `f` on `handle` from sync code. `block_in_place` panics on a
+    /// current thread runtime, so there `f` is driven from a scoped thread,
+    /// like [`WrapDatabaseAsync`] does
+    fn block_on<F>(handle: &tokio::runtime::Handle, f: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send
+    {
+        match tokio::runtime::Handle::try_current().map(|current| current.runtime_flavor()) {
+            Ok(RuntimeFlavor::CurrentThread) => std::thread::scope(|s| {
+                s.spawn(|| handle.block_on(f))
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            }),
+            Ok(_) => tokio::task::block_in_place(|| handle.block_on(f)),
+            Err(_) => handle.block_on(f)
+        }
+    }
+
+    /// the [`EthRevm::InnerDb`] of the rpc clients. [`AlloyDB`] keeps the block
+    /// it reads at private, so it is kept here as well to check storage reads
+    /// made by block id against it
+    pub struct RpcDatabaseRef<N: Network, P: Provider<N>> {
+        db:       WrapDatabaseAsync<AlloyDB<N, P>>,
+        provider: P,
+        block_id: BlockId
+    }
+
+    impl<N: Network, P: Provider<N> + Clone> RpcDatabaseRef<N, P> {
+        pub fn new(provider: P, block_id: BlockId, handle: tokio::runtime::Handle) -> Self {
+            let db = WrapDatabaseAsync::with_handle(AlloyDB::new(provider.clone(), block_id), handle);
+            Self { db, provider, block_id }
+        }
+
+        pub fn block_id(&self) -> BlockId {
+            self.block_id
+        }
+
+        /// errors if `block_id` is not the id the db reads at. block hashes
+        /// match regardless of `require_canonical`
+        pub fn ensure_block(&self, block_id: BlockId) -> eyre::Result<()> {
+            let same_block = match (block_id, self.block_id) {
+                (BlockId::Hash(requested), BlockId::Hash(pinned)) => requested.block_hash == pinned.block_hash,
+                (requested, pinned) => requested == pinned
+            };
+
+            if !same_block {
+                eyre::bail!("requested state for {block_id} but the db reads at {}", self.block_id);
+            }
+
+            Ok(())
+        }
+    }
+
+    impl<N: Network, P: Provider<N>> DatabaseRef for RpcDatabaseRef<N, P> {
+        type Error = <WrapDatabaseAsync<AlloyDB<N, P>> as DatabaseRef>::Error;
+
+        fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+            self.db.basic_ref(address)
+        }
+
+        fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+            self.db.code_by_hash_ref(code_hash)
+        }
+
+        fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+            self.db.storage_ref(address, index)
+        }
+
+        fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+            self.db.block_hash_ref(number)
+        }
+    }
+
+    #[cfg(feature = "uniswap-storage")]
+    mod _uniswap_storage {
+        use alloy_primitives::{StorageKey, StorageValue};
+        use uniswap_storage::{StorageSlotFetcher, StorageSlotFetcherSync};
+
+        use super::*;
+
+        #[async_trait::async_trait]
+        impl<N: Network, P: Provider<N> + Clone> StorageSlotFetcher for RpcDatabaseRef<N, P> {
+            async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+                self.ensure_block(block_id)?;
+
+                Ok(self
+                    .provider
+                    .get_storage_at(address, key.into())
+                    .block_id(self.block_id)
+                    .await?)
+            }
+        }
+
+        impl<N: Network, P: Provider<N> + Clone> StorageSlotFetcherSync for RpcDatabaseRef<N, P> {
+            fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+                self.ensure_block(block_id)?;
+
+                self.db
+                    .storage_ref(address, key.into())
+                    .map_err(|e| eyre::eyre!("{e:?}"))
+            }
+        }
+    }
+
+    impl<P, N> EthRpcClient<P, N>
+    where
+        P: Provider<N> + Clone,
+        N: Network
+    {
+        /// [`EthRevm::make_mainnet_revm_with_overrides`], with the state `txs`
+        /// read fetched concurrently into the cache db up front. see
+        /// [`prefetch_state`]
+        pub fn make_prefetched_mainnet_revm(
+            &self,
+            params: &AsyncEthRevmParams,
+            txs: &[TransactionRequest],
+            overrides: &EvmOverrides,
+            config: PrefetchConfig
+        ) -> eyre::Result<MainnetRevmEvm<<Self as EthRevm>::InnerDb>> {
+            let mut evm = self.make_mainnet_revm_with_overrides(params, overrides, false)?;
+
+            let ctx = evm.ctx_ref();
+            let cache = block_on(
+                &params.handle,
+                prefetch_state(
+                    &self.provider,
+                    params.block_id,
+                    &ctx.block,
+                    &ctx.cfg,
+                    ctx.db_ref().cache.clone(),
+                    txs,
+                    config
+                )
+            )?;
+            evm.ctx_mut().db_mut().cache = cache;
+
+            Ok(evm)
+        }
+
+        /// [`EthRevm::simulate_tx_with_overrides`], with the state `tx` reads
+        /// prefetched
+        pub fn simulate_tx_prefetched(
+            &self,
+            params: &AsyncEthRevmParams,
+            tx: TransactionRequest,
+            overrides: &EvmOverrides,
+            config: PrefetchConfig
+        ) -> eyre::Result<SimulationOutcome> {
+            let mut evm = self.make_prefetched_mainnet_revm(params, std::slice::from_ref(&tx), overrides, config)?;
+            simulate_mainnet_evm(&mut evm, tx)
+        }
+    }
+
+    #[cfg(feature = "fork-cache")]
+    impl<P, N> EthRpcClient<P, N>
+    where
+        P: Provider<N> + Clone,
+        N: Network
+    {
+        /// persists every read at a pinned block under `dir`, so simulations
+        /// at that block never reach the node once warmed up
+        pub fn with_fork_cache(self, dir: impl Into<std::path::PathBuf>) -> crate::traits::ForkCache<Self> {
+            crate::traits::ForkCache::new(self, dir)
+        }
+    }
 
     impl<P, N> EthRevm for EthRpcClient<P, N>
     where
         P: Provider<N> + Clone,
         N: Network
     {
-        type InnerDb = WrapDatabaseAsync<AlloyDB<N, P>>;
+        type InnerDb = RpcDatabaseRef<N, P>;
         type Params = AsyncEthRevmParams;
 
         fn make_inner_db(&self, params: &AsyncEthRevmParams) -> eyre::Result<Self::InnerDb> {
-            Ok(WrapDatabaseAsync::with_handle(AlloyDB::new(self.provider.clone(), params.block_id), params.handle.clone()))
+            Ok(RpcDatabaseRef::new(self.provider.clone(), params.block_id, params.handle.clone()))
+        }
+
+        fn make_block_env(&self, params: &AsyncEthRevmParams) -> eyre::Result<BlockEnv> {
+            rpc_block_env(&self.provider, params)
+        }
+
+        fn make_cfg_env(&self, params: &AsyncEthRevmParams, block_env: &BlockEnv) -> eyre::Result<CfgEnv> {
+            Ok(rpc_cfg_env(params, block_env))
         }
     }
 
@@ -125,11 +370,72 @@ mod revm_impl {
     where
         N: Network
     {
-        type InnerDb = WrapDatabaseAsync<AlloyDB<N, RootProvider<N>>>;
+        type InnerDb = RpcDatabaseRef<N, RootProvider<N>>;
         type Params = AsyncEthRevmParams;
 
         fn make_inner_db(&self, params: &AsyncEthRevmParams) -> eyre::Result<Self::InnerDb> {
-            Ok(WrapDatabaseAsync::with_handle(AlloyDB::new(self.clone(), params.block_id), params.handle.clone()))
+            Ok(RpcDatabaseRef::new(self.clone(), params.block_id, params.handle.clone()))
+        }
+
+        fn make_block_env(&self, params: &AsyncEthRevmParams) -> eyre::Result<BlockEnv> {
+            rpc_block_env(self, params)
+        }
+
+        fn make_cfg_env(&self, params: &AsyncEthRevmParams, block_env: &BlockEnv) -> eyre::Result<CfgEnv> {
+            Ok(rpc_cfg_env(params, block_env))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use alloy_consensus::Header;
+        use alloy_eips::RpcBlockHash;
+        use alloy_network::Ethereum;
+        use revm::primitives::hardfork::SpecId;
+
+        use super::*;
+
+        #[tokio::test]
+        async fn test_block_on_current_thread_runtime() {
+            assert_eq!(block_on(&tokio::runtime::Handle::current(), async { 1 }), 1);
+        }
+
+        #[tokio::test]
+        async fn test_rpc_cfg_env() {
+            let params = |chain_id| AsyncEthRevmParams {
+                block_id: BlockId::latest(),
+                chain_id,
+                handle: tokio::runtime::Handle::current()
+            };
+            let block_env =
+                BlockEnv { number: U256::from(20_000_000), timestamp: U256::from(1_718_000_000), ..Default::default() };
+
+            assert_eq!(rpc_cfg_env(&params(1), &block_env).spec, SpecId::CANCUN);
+            assert_eq!(rpc_cfg_env(&params(12345), &block_env).spec, SpecId::default());
+        }
+
+        #[test]
+        fn test_header_blob_params() {
+            let cancun = Header { excess_blob_gas: Some(0), ..Default::default() };
+            let prague = Header { excess_blob_gas: Some(0), requests_hash: Some(B256::ZERO), ..Default::default() };
+
+            assert_eq!(header_blob_params(12345, &Header::default()), None);
+            assert_eq!(header_blob_params(12345, &cancun), Some(BlobParams::cancun()));
+            assert_eq!(header_blob_params(12345, &prague), Some(BlobParams::prague()));
+        }
+
+        #[tokio::test]
+        async fn test_rpc_database_ref_checks_block() {
+            let provider = RootProvider::<Ethereum>::new_http("http://localhost:8545".parse().unwrap());
+            let hash = B256::repeat_byte(1);
+            let db = RpcDatabaseRef::new(provider, BlockId::hash(hash), tokio::runtime::Handle::current());
+
+            assert!(db.ensure_block(BlockId::hash(hash)).is_ok());
+            assert!(db.ensure_block(BlockId::Hash(RpcBlockHash::from_hash(hash, Some(true)))).is_ok());
+
+            assert!(db.ensure_block(BlockId::hash(B256::ZERO)).is_err());
+            assert!(db.ensure_block(BlockId::latest()).is_err());
+            assert!(db.ensure_block(BlockId::number(1)).is_err());
         }
     }
 }
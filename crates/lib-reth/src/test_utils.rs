@@ -2,6 +2,9 @@ use std::fmt::Debug;
 
 use futures::{Stream, StreamExt};
 
+#[cfg(all(feature = "reth-db", not(feature = "ci")))]
+pub use local_node::*;
+
 pub async fn stream_timeout<O: Debug>(
     stream: impl Stream<Item = O> + Unpin,
     values: usize,
@@ -26,3 +29,110 @@ pub async fn stream_timeout<O: Debug>(
 
     Ok(())
 }
+
+/// an http json-rpc endpoint on localhost that answers every request with
+/// `result`, a json value. the bodies of the requests it gets are sent on the
+/// returned channel
+pub async fn mock_rpc_endpoint(
+    result: String
+) -> eyre::Result<(String, tokio::sync::mpsc::UnboundedReceiver<String>)> {
+    mock_rpc_handler(move |_| result.clone()).await
+}
+
+/// [`mock_rpc_endpoint`], answering each request with the json value
+/// `handler` returns for its body
+pub async fn mock_rpc_handler(
+    handler: impl Fn(&str) -> String + Send + Sync + 'static
+) -> eyre::Result<(String, tokio::sync::mpsc::UnboundedReceiver<String>)> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let (requests_tx, requests_rx) = tokio::sync::mpsc::unbounded_channel();
+    let handler = std::sync::Arc::new(handler);
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let requests_tx = requests_tx.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let body = loop {
+                    let Ok(n) = stream.read(&mut chunk).await else { return };
+                    if n == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&chunk[..n]);
+
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((headers, body)) = text.split_once("\r\n\r\n") else { continue };
+                    let content_length = headers
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or_default();
+                    if body.len() >= content_length {
+                        break body.to_string();
+                    }
+                };
+
+                let id = body
+                    .split_once("\"id\":")
+                    .and_then(|(_, rest)| rest.split([',', '}']).next())
+                    .unwrap_or("1")
+                    .trim()
+                    .to_string();
+                let result = handler(&body);
+                let response = format!(r#"{{"jsonrpc":"2.0","id":{id},"result":{result}}}"#);
+                let _ = requests_tx.send(body);
+
+                let _ = stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: \
+                             close\r\n\r\n{response}",
+                            response.len()
+                        )
+                        .as_bytes()
+                    )
+                    .await;
+            });
+        }
+    });
+
+    Ok((url, requests_rx))
+}
+
+#[cfg(all(feature = "reth-db", not(feature = "ci")))]
+mod local_node {
+    use alloy_primitives::{Address, U256, address};
+    use alloy_rpc_types::TransactionRequest;
+    use eth_network_exts::mainnet::MainnetExt;
+    use reth_chainspec::MAINNET;
+
+    use crate::reth_libmdbx::{RethNodeClient, RethNodeClientBuilder};
+
+    pub const MAINNET_DB_PATH: &str = "/var/lib/eth/mainnet/reth/";
+
+    pub const WETH: Address = address!("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+    /// a client over the mainnet node at [`MAINNET_DB_PATH`]
+    pub fn mainnet_client() -> RethNodeClient<MainnetExt> {
+        RethNodeClientBuilder::<MainnetExt>::new(MAINNET_DB_PATH, 1000, MAINNET.clone(), None, None)
+            .build()
+            .unwrap()
+    }
+
+    /// `WETH.deposit()` of 1 eth from the zero address
+    pub fn weth_deposit_tx() -> TransactionRequest {
+        TransactionRequest::default()
+            .from(Address::ZERO)
+            .to(WETH)
+            .value(U256::from(10).pow(U256::from(18)))
+            .input(vec![0xd0, 0xe3, 0x0d, 0xb0].into())
+    }
+}
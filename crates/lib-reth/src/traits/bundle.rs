@@ -0,0 +1,209 @@
+use alloy_primitives::{Address, U256};
+use alloy_rpc_types::TransactionRequest;
+use revm::{
+    DatabaseCommit, DatabaseRef,
+    context_interface::{Block, ContextTr},
+    handler::EvmTr,
+    state::EvmState
+};
+use revm_database::CacheDB;
+
+use crate::traits::{MainnetRevmEvm, SimulationOutcome, TransactionValidationError, execute_mainnet_evm};
+
+#[derive(Debug, Clone, Default)]
+pub struct BundleTransaction {
+    pub tx:             TransactionRequest,
+    /// the transaction failing does not fail the bundle. its effects, gas
+    /// payment included, are kept. a transaction that fails validation has
+    /// none
+    pub revert_allowed: bool
+}
+
+impl From<TransactionRequest> for BundleTransaction {
+    fn from(tx: TransactionRequest) -> Self {
+        Self { tx, revert_allowed: false }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleOutcome {
+    /// one outcome per executed transaction, in bundle order. an atomic
+    /// bundle stops at the first failed transaction
+    pub transactions:     Vec<SimulationOutcome>,
+    /// indices of the transactions that failed without being allowed to
+    /// revert. their effects are discarded
+    pub failed:           Vec<usize>,
+    /// gas used by the transactions whose effects were kept
+    pub gas_used:         u64,
+    /// the coinbase's balance increase over the bundle, priority fees and
+    /// direct transfers included
+    pub coinbase_payment: U256
+}
+
+impl BundleOutcome {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// simulates `txs` in order on `evm`, each seeing the effects of the ones
+/// before it. the kept effects stay in the evm's `CacheDB`. a transaction that
+/// fails validation is a failed transaction with a
+/// [`SimulationStatus::Invalid`](crate::traits::SimulationStatus::Invalid)
+/// status. if `atomic`, the first failed transaction discards the effects of
+/// the whole bundle
+pub fn simulate_mainnet_bundle<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    txs: Vec<BundleTransaction>,
+    atomic: bool
+) -> eyre::Result<BundleOutcome> {
+    simulate_bundle_with(evm, txs, atomic, execute_mainnet_evm)
+}
+
+fn simulate_bundle_with<E, DB>(
+    evm: &mut E,
+    txs: Vec<BundleTransaction>,
+    atomic: bool,
+    mut execute: impl FnMut(&mut E, TransactionRequest) -> eyre::Result<(SimulationOutcome, EvmState)>
+) -> eyre::Result<BundleOutcome>
+where
+    E: EvmTr<Context: ContextTr<Db = CacheDB<DB>>>,
+    DB: DatabaseRef
+{
+    let coinbase = evm.ctx_ref().block().beneficiary();
+    let balance_before = balance(evm.ctx_ref().db_ref(), coinbase)?;
+    let snapshot = evm.ctx_ref().db_ref().cache.clone();
+
+    let mut outcome = BundleOutcome {
+        transactions:     Vec::with_capacity(txs.len()),
+        failed:           Vec::new(),
+        gas_used:         0,
+        coinbase_payment: U256::ZERO
+    };
+
+    for (idx, BundleTransaction { tx, revert_allowed }) in txs.into_iter().enumerate() {
+        let (tx_outcome, state) = match execute(evm, tx) {
+            Ok(executed) => executed,
+            Err(e) => match e.downcast::<TransactionValidationError>() {
+                Ok(invalid) => (SimulationOutcome::invalid(invalid), EvmState::default()),
+                Err(e) => {
+                    if atomic {
+                        evm.ctx_mut().db_mut().cache = snapshot;
+                    }
+                    return Err(e);
+                }
+            }
+        };
+
+        if tx_outcome.is_success() || revert_allowed {
+            outcome.gas_used += tx_outcome.gas_used;
+            outcome.transactions.push(tx_outcome);
+            evm.ctx_mut().db_mut().commit(state);
+            continue;
+        }
+
+        outcome.transactions.push(tx_outcome);
+        outcome.failed.push(idx);
+
+        if atomic {
+            evm.ctx_mut().db_mut().cache = snapshot;
+            outcome.gas_used = 0;
+            return Ok(outcome);
+        }
+    }
+
+    let balance_after = balance(evm.ctx_ref().db_ref(), coinbase)?;
+    outcome.coinbase_payment = balance_after.saturating_sub(balance_before);
+
+    Ok(outcome)
+}
+
+fn balance<DB: DatabaseRef>(db: &CacheDB<DB>, address: Address) -> eyre::Result<U256> {
+    Ok(db
+        .basic_ref(address)
+        .map_err(|e| eyre::eyre!("{e:?}"))?
+        .map(|info| info.balance)
+        .unwrap_or_default())
+}
+
+#[cfg(feature = "op-revm")]
+pub use op_impl::simulate_op_bundle;
+
+#[cfg(feature = "op-revm")]
+mod op_impl {
+    use super::*;
+    use crate::traits::{OptimismRevmEvm, execute_op_evm};
+
+    /// [`simulate_mainnet_bundle`] on the op evm
+    pub fn simulate_op_bundle<DB: DatabaseRef>(
+        evm: &mut OptimismRevmEvm<DB>,
+        txs: Vec<BundleTransaction>,
+        atomic: bool
+    ) -> eyre::Result<BundleOutcome> {
+        simulate_bundle_with(evm, txs, atomic, |evm, tx| execute_op_evm(evm, tx, None, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use revm_database::EmptyDB;
+
+    use super::*;
+    use crate::traits::{SimulationStatus, empty_mainnet_revm};
+
+    #[test]
+    fn test_invalid_transaction_fails_bundle() {
+        let from = address!("0x0000000000000000000000000000000000000001");
+        let to = address!("0x0000000000000000000000000000000000000002");
+        let transfer = TransactionRequest::default().from(from).to(to);
+        let txs = vec![transfer.clone().into(), transfer.nonce(5).into()];
+
+        let mut evm = empty_mainnet_revm(CacheDB::new(EmptyDB::default()), 1, false);
+        let outcome = simulate_mainnet_bundle(&mut evm, txs.clone(), false).unwrap();
+        assert_eq!(outcome.failed, vec![1]);
+        assert!(matches!(outcome.transactions[1].status, SimulationStatus::Invalid { .. }));
+        assert_eq!(evm.ctx_ref().db_ref().basic_ref(from).unwrap().unwrap().nonce, 1);
+
+        let mut evm = empty_mainnet_revm(CacheDB::new(EmptyDB::default()), 1, false);
+        let outcome = simulate_mainnet_bundle(&mut evm, txs, true).unwrap();
+        assert_eq!(outcome.failed, vec![1]);
+        assert_eq!(outcome.gas_used, 0);
+        assert!(!evm.ctx_ref().db_ref().cache.accounts.contains_key(&from));
+    }
+
+    #[cfg(all(feature = "reth-db", not(feature = "ci")))]
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_simulate_bundle() {
+        use alloy_eips::BlockId;
+        use alloy_primitives::{Address, U256};
+
+        use crate::{
+            test_utils::{WETH, mainnet_client, weth_deposit_tx},
+            traits::{EthRevm, EthRevmParams}
+        };
+
+        let client = mainnet_client();
+        let params = EthRevmParams { block_id: BlockId::latest(), chain_id: 1 };
+
+        // WETH.deposit() twice, then WETH.withdraw(type(uint256).max)
+        let deposit = weth_deposit_tx().max_priority_fee_per_gas(1_000_000_000);
+        let mut withdraw_input = vec![0x2e, 0x1a, 0x7d, 0x4d];
+        withdraw_input.extend(U256::MAX.to_be_bytes::<32>());
+        let withdraw = TransactionRequest::default()
+            .from(Address::ZERO)
+            .to(WETH)
+            .input(withdraw_input.into());
+        let txs = vec![deposit.clone().into(), deposit.into(), withdraw.into()];
+
+        let outcome = client.simulate_bundle(&params, txs.clone(), false).unwrap();
+        assert_eq!(outcome.transactions.len(), 3);
+        assert_eq!(outcome.failed, vec![2]);
+        assert!(outcome.coinbase_payment > U256::ZERO);
+
+        let outcome = client.simulate_bundle(&params, txs, true).unwrap();
+        assert!(!outcome.is_success());
+        assert_eq!(outcome.gas_used, 0);
+    }
+}
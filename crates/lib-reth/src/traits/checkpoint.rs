@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering}
+};
+
+use alloy_primitives::Address;
+use alloy_rpc_types::TransactionRequest;
+use revm::{DatabaseCommit, DatabaseRef, context_interface::ContextTr, handler::EvmTr, state::EvmState};
+use revm_database::{CacheDB, DbAccount};
+
+use crate::traits::{MainnetRevmEvm, SimulationOutcome, execute_mainnet_evm};
+
+/// a point [`LayeredState::revert_to`] can roll back to. it is invalidated by
+/// reverting to an earlier checkpoint or by [`LayeredState::commit`]. every
+/// checkpoint has an id of its own, so a stale one is never mistaken for a
+/// later checkpoint taken at the same depth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateCheckpoint {
+    depth: usize,
+    id:    u64
+}
+
+/// checkpoints over a [`CacheDB`]. every layer keeps the cache entries of the
+/// accounts committed to since it was taken, as they were before, so taking a
+/// checkpoint is free and reverting only touches what changed. what was read
+/// from the inner database stays cached across reverts
+#[derive(Debug, Default)]
+pub struct LayeredState {
+    layers: Vec<(u64, HashMap<Address, Option<DbAccount>>)>
+}
+
+impl LayeredState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the number of live checkpoints
+    pub fn depth(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn checkpoint(&mut self) -> StateCheckpoint {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        self.layers.push((id, HashMap::new()));
+        StateCheckpoint { depth: self.layers.len() - 1, id }
+    }
+
+    /// commits `state` to `db`, remembering what it replaced
+    pub fn commit_state<DB>(&mut self, db: &mut CacheDB<DB>, state: EvmState) {
+        if let Some((_, layer)) = self.layers.last_mut() {
+            for (address, _) in state.iter().filter(|(_, account)| account.is_touched()) {
+                layer
+                    .entry(*address)
+                    .or_insert_with(|| db.cache.accounts.get(address).cloned());
+            }
+        }
+
+        db.commit(state);
+    }
+
+    /// rolls `db` back to the state it had when `checkpoint` was taken,
+    /// dropping `checkpoint` and every later one
+    pub fn revert_to<DB>(&mut self, db: &mut CacheDB<DB>, checkpoint: StateCheckpoint) -> eyre::Result<()> {
+        if self.layers.get(checkpoint.depth).map(|(id, _)| *id) != Some(checkpoint.id) {
+            eyre::bail!("checkpoint {} is no longer live, the depth is {}", checkpoint.id, self.layers.len());
+        }
+
+        for (_, layer) in self.layers.drain(checkpoint.depth..).rev() {
+            for (address, account) in layer {
+                match account {
+                    Some(account) => db.cache.accounts.insert(address, account),
+                    None => db.cache.accounts.remove(&address)
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// keeps the current state, dropping every checkpoint
+    pub fn commit(&mut self) {
+        self.layers.clear();
+    }
+}
+
+/// a mainnet evm whose committed transactions can be checkpointed and rolled
+/// back, so alternative sequences can branch from the same warmed state
+pub struct LayeredMainnetRevm<DB: DatabaseRef> {
+    evm:    MainnetRevmEvm<DB>,
+    layers: LayeredState
+}
+
+impl<DB: DatabaseRef> LayeredMainnetRevm<DB> {
+    pub fn new(evm: MainnetRevmEvm<DB>) -> Self {
+        Self { evm, layers: LayeredState::new() }
+    }
+
+    pub fn evm(&self) -> &MainnetRevmEvm<DB> {
+        &self.evm
+    }
+
+    pub fn evm_mut(&mut self) -> &mut MainnetRevmEvm<DB> {
+        &mut self.evm
+    }
+
+    pub fn into_evm(self) -> MainnetRevmEvm<DB> {
+        self.evm
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.depth()
+    }
+
+    /// simulates `tx` and commits its effects, whatever its status
+    pub fn simulate_and_commit(&mut self, tx: TransactionRequest) -> eyre::Result<SimulationOutcome> {
+        let (outcome, state) = execute_mainnet_evm(&mut self.evm, tx)?;
+        self.layers
+            .commit_state(self.evm.ctx_mut().db_mut(), state);
+
+        Ok(outcome)
+    }
+
+    pub fn checkpoint(&mut self) -> StateCheckpoint {
+        self.layers.checkpoint()
+    }
+
+    pub fn revert_to(&mut self, checkpoint: StateCheckpoint) -> eyre::Result<()> {
+        self.layers
+            .revert_to(self.evm.ctx_mut().db_mut(), checkpoint)
+    }
+
+    pub fn commit(&mut self) {
+        self.layers.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{U256, address};
+    use revm::state::{Account, AccountInfo};
+    use revm_database::EmptyDB;
+
+    use super::*;
+
+    fn balance_change(address: Address, balance: u64) -> EvmState {
+        let mut account = Account::from(AccountInfo { balance: U256::from(balance), ..Default::default() });
+        account.mark_touch();
+        [(address, account)].into_iter().collect()
+    }
+
+    fn balance(db: &CacheDB<EmptyDB>, address: Address) -> Option<U256> {
+        db.basic_ref(address).unwrap().map(|info| info.balance)
+    }
+
+    #[test]
+    fn test_layered_state_revert() {
+        let a = address!("0x0000000000000000000000000000000000000001");
+        let b = address!("0x0000000000000000000000000000000000000002");
+        let mut db = CacheDB::new(EmptyDB::default());
+        let mut layers = LayeredState::new();
+
+        layers.commit_state(&mut db, balance_change(a, 1));
+        let base = layers.checkpoint();
+
+        layers.commit_state(&mut db, balance_change(a, 2));
+        let route_a = layers.checkpoint();
+        layers.commit_state(&mut db, balance_change(b, 3));
+        assert_eq!(balance(&db, a), Some(U256::from(2)));
+        assert_eq!(balance(&db, b), Some(U256::from(3)));
+
+        layers.revert_to(&mut db, route_a).unwrap();
+        assert_eq!(balance(&db, b), None);
+        assert_eq!(layers.depth(), 1);
+
+        layers.revert_to(&mut db, base).unwrap();
+        assert_eq!(balance(&db, a), Some(U256::from(1)));
+        assert!(layers.revert_to(&mut db, route_a).is_err());
+
+        // taken at the depth `base` had
+        let route_b = layers.checkpoint();
+        assert!(layers.revert_to(&mut db, base).is_err());
+        layers.commit_state(&mut db, balance_change(b, 4));
+        layers.commit();
+        assert!(layers.revert_to(&mut db, route_b).is_err());
+        assert_eq!(balance(&db, b), Some(U256::from(4)));
+    }
+}
@@ -0,0 +1,218 @@
+use alloy_eips::eip2930::{AccessList, AccessListResult};
+use alloy_primitives::U256;
+use alloy_rpc_types::TransactionRequest;
+use revm::{
+    DatabaseRef, ExecuteEvm,
+    context_interface::{
+        Cfg, ContextTr,
+        result::{EVMError, ExecutionResult}
+    },
+    handler::EvmTr
+};
+use revm_inspectors::access_list::AccessListInspector;
+
+use crate::traits::{MainnetRevmEvm, disable_base_fee_for, fill_request, inspect_mainnet_evm, tx_env_from_request};
+
+/// adding a slot to the access list changes the gas of the accesses, which can
+/// change the path taken and so the slots touched
+const MAX_ACCESS_LIST_ROUNDS: usize = 8;
+
+/// `eth_createAccessList`, run locally. the transaction is re-run with the
+/// access list it produced until the list stops changing
+pub fn create_access_list_mainnet_evm<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB, AccessListInspector>,
+    mut tx: TransactionRequest
+) -> eyre::Result<AccessListResult> {
+    let mut access_list = tx.access_list.clone().unwrap_or_default();
+
+    for _ in 0..MAX_ACCESS_LIST_ROUNDS {
+        tx.access_list = Some(access_list.clone());
+        evm.inspector = AccessListInspector::new(access_list.clone());
+
+        let res = inspect_mainnet_evm(evm, tx.clone())?;
+        let next = std::mem::replace(&mut evm.inspector, AccessListInspector::new(AccessList::default()))
+            .into_access_list();
+
+        if next == access_list {
+            return Ok(AccessListResult {
+                access_list,
+                gas_used: U256::from(res.result.gas_used()),
+                error: failure_reason(&res.result)
+            });
+        }
+        access_list = next;
+    }
+
+    eyre::bail!("access list did not converge after {MAX_ACCESS_LIST_ROUNDS} rounds")
+}
+
+/// `eth_estimateGas`, run locally. binary searches the lowest gas limit `tx`
+/// succeeds with, ignoring the caller's balance and the block gas limit. the
+/// search never goes above the tx gas cap of the spec (eip-7825). the cfg of
+/// `evm` is restored before returning
+pub fn estimate_gas_mainnet_evm<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    tx: TransactionRequest
+) -> eyre::Result<u64> {
+    let cfg = evm.ctx_ref().cfg.clone();
+    evm.ctx_mut().modify_cfg(|cfg| {
+        cfg.disable_balance_check = true;
+        cfg.disable_block_gas_limit = true;
+    });
+
+    let gas = search_gas_limit(evm, &tx);
+    evm.ctx_mut().modify_cfg(|c| *c = cfg);
+
+    gas
+}
+
+fn search_gas_limit<DB: DatabaseRef>(evm: &mut MainnetRevmEvm<DB>, tx: &TransactionRequest) -> eyre::Result<u64> {
+    let mut hi = tx
+        .gas
+        .unwrap_or(evm.ctx_ref().block.gas_limit)
+        .min(evm.ctx_ref().cfg.tx_gas_limit_cap());
+    let gas_used = match run_with_gas(evm, tx, hi)? {
+        Some(result) if result.is_success() => result.gas_used(),
+        Some(result) => eyre::bail!(
+            "transaction fails with the full gas limit of {hi}: {}",
+            failure_reason(&result).unwrap_or_default()
+        ),
+        None => eyre::bail!("transaction is invalid with the full gas limit of {hi}")
+    };
+
+    // refunds and the 63/64 rule mean the limit needed is above the gas used,
+    // but rarely by much
+    let mut lo = gas_used.saturating_sub(1);
+    let optimistic = gas_used.saturating_mul(64) / 63;
+    if optimistic < hi {
+        if succeeds(evm, tx, optimistic)? {
+            hi = optimistic;
+        } else {
+            lo = optimistic;
+        }
+    }
+
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if succeeds(evm, tx, mid)? {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    Ok(hi)
+}
+
+fn succeeds<DB: DatabaseRef>(evm: &mut MainnetRevmEvm<DB>, tx: &TransactionRequest, gas: u64) -> eyre::Result<bool> {
+    Ok(run_with_gas(evm, tx, gas)?.is_some_and(|result| result.is_success()))
+}
+
+/// `None` if the transaction is invalid with `gas`, e.g. below its intrinsic
+/// gas
+fn run_with_gas<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    tx: &TransactionRequest,
+    gas: u64
+) -> eyre::Result<Option<ExecutionResult>> {
+    let mut tx = tx.clone();
+    tx.gas = Some(gas);
+    let tx = fill_request(tx, &evm.ctx_ref().block, &evm.ctx_ref().cfg, evm.ctx_ref().db_ref())?;
+
+    let disable_base_fee = disable_base_fee_for(&mut evm.ctx_mut().cfg, &tx);
+    let res = evm.transact(tx_env_from_request(&tx));
+    evm.ctx_mut().cfg.disable_base_fee = disable_base_fee;
+
+    match res {
+        Ok(res) => Ok(Some(res.result)),
+        Err(EVMError::Transaction(_)) => Ok(None),
+        Err(e) => Err(eyre::eyre!("{e:?}"))
+    }
+}
+
+fn failure_reason(result: &ExecutionResult) -> Option<String> {
+    match result {
+        ExecutionResult::Success { .. } => None,
+        ExecutionResult::Revert { output, .. } => Some(
+            alloy_sol_types::decode_revert_reason(output).unwrap_or_else(|| "execution reverted".to_string())
+        ),
+        ExecutionResult::Halt { reason, .. } => Some(format!("{reason:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use revm::primitives::hardfork::SpecId;
+    use revm_database::{CacheDB, EmptyDB};
+
+    use super::*;
+    use crate::traits::empty_mainnet_revm;
+
+    #[test]
+    fn test_estimate_gas_on_osaka() {
+        let from = address!("0x0000000000000000000000000000000000000001");
+        let to = address!("0x0000000000000000000000000000000000000002");
+
+        let mut evm = empty_mainnet_revm(CacheDB::new(EmptyDB::default()), 1, false);
+        evm.ctx_mut().cfg.spec = SpecId::OSAKA;
+        evm.ctx_mut().block.gas_limit = 60_000_000;
+
+        let gas = estimate_gas_mainnet_evm(&mut evm, TransactionRequest::default().from(from).to(to)).unwrap();
+        assert_eq!(gas, 21_000);
+        assert!(!evm.ctx_ref().cfg.disable_balance_check);
+        assert!(!evm.ctx_ref().cfg.disable_block_gas_limit);
+
+        // a gas limit below the intrinsic gas errors, which must not leave the
+        // checks disabled
+        let tx = TransactionRequest::default()
+            .from(from)
+            .to(to)
+            .gas_limit(20_000);
+        assert!(estimate_gas_mainnet_evm(&mut evm, tx).is_err());
+        assert!(!evm.ctx_ref().cfg.disable_balance_check);
+        assert!(!evm.ctx_ref().cfg.disable_block_gas_limit);
+    }
+
+    #[cfg(all(feature = "reth-db", not(feature = "ci")))]
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_estimate_gas_and_access_list() {
+        use alloy_eips::BlockId;
+        use alloy_primitives::U256;
+        use alloy_rpc_types::state::EvmOverrides;
+
+        use crate::{
+            test_utils::{mainnet_client, weth_deposit_tx},
+            traits::{EthRevm, EthRevmParams}
+        };
+
+        let client = mainnet_client();
+        let params = EthRevmParams { block_id: BlockId::latest(), chain_id: 1 };
+        let overrides = EvmOverrides::default();
+        let tx = weth_deposit_tx();
+
+        let gas = client
+            .estimate_gas(&params, tx.clone(), &overrides)
+            .unwrap();
+        assert!(gas > 21_000 && gas < 100_000);
+        assert!(
+            client
+                .simulate_tx(&params, tx.clone().gas_limit(gas))
+                .unwrap()
+                .is_success()
+        );
+        assert!(
+            !client
+                .simulate_tx(&params, tx.clone().gas_limit(gas - 1))
+                .unwrap()
+                .is_success()
+        );
+
+        let access_list = client
+            .create_access_list(&params, tx, &overrides)
+            .unwrap();
+        assert!(access_list.error.is_none());
+        assert!(access_list.gas_used > U256::ZERO);
+    }
+}
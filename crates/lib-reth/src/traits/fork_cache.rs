@@ -0,0 +1,512 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, PoisonError, RwLock,
+        atomic::{AtomicU64, Ordering}
+    }
+};
+
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_primitives::{Address, B256, ChainId, U256};
+use eyre::eyre;
+use revm::{
+    DatabaseRef,
+    context::{BlockEnv, CfgEnv},
+    state::{AccountInfo, Bytecode}
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::traits::{EthRevm, EthRevmInput};
+
+/// everything read from the inner database at one block
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ForkCacheEntries {
+    block_env:    Option<BlockEnv>,
+    accounts:     HashMap<Address, Option<AccountInfo>>,
+    contracts:    HashMap<B256, Bytecode>,
+    storage:      HashMap<Address, HashMap<U256, U256>>,
+    block_hashes: HashMap<u64, B256>
+}
+
+/// the cached state of one `(chain id, block hash)`, backed by a json file
+#[derive(Debug)]
+pub struct ForkCacheState {
+    path:    PathBuf,
+    entries: RwLock<ForkCacheEntries>,
+    dirty:   Mutex<bool>
+}
+
+impl ForkCacheState {
+    /// loads the cache of `block_hash` on `chain_id` from `dir`, empty if it
+    /// was never written
+    pub fn load(dir: &Path, chain_id: ChainId, block_hash: B256) -> eyre::Result<Self> {
+        let path = dir
+            .join(chain_id.to_string())
+            .join(format!("{block_hash}.json"));
+        let entries = read_json(&path)?;
+
+        Ok(Self { path, entries: RwLock::new(entries), dirty: Mutex::new(false) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// writes the cache to disk if anything was added since the last flush
+    pub fn flush(&self) -> eyre::Result<()> {
+        let mut dirty = self.dirty.lock().map_err(|e| eyre!("{e}"))?;
+        if !*dirty {
+            return Ok(());
+        }
+
+        write_json(&self.path, &*self.entries.read().map_err(|e| eyre!("{e}"))?)?;
+
+        *dirty = false;
+        Ok(())
+    }
+
+    // the entries are only ever replaced whole, so a panic elsewhere never
+    // leaves them half written and a poisoned lock is still safe to use
+    fn get<T>(&self, f: impl FnOnce(&ForkCacheEntries) -> Option<T>) -> Option<T> {
+        f(&self.entries.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn insert(&self, f: impl FnOnce(&mut ForkCacheEntries)) {
+        f(&mut self.entries.write().unwrap_or_else(PoisonError::into_inner));
+        *self.dirty.lock().unwrap_or_else(PoisonError::into_inner) = true;
+    }
+}
+
+/// the default value if `path` was never written
+fn read_json<T: DeserializeOwned + Default>(path: &Path) -> eyre::Result<T> {
+    match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| eyre!("corrupt fork cache {}: {e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into())
+    }
+}
+
+/// written to a file of its own next to `path` and renamed, so a crash never
+/// leaves a truncated cache behind and two processes never write the same tmp
+/// file
+fn write_json<T: Serialize>(path: &Path, value: &T) -> eyre::Result<()> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}.tmp", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+    let tmp = path.with_file_name(name);
+
+    std::fs::write(&tmp, serde_json::to_vec(value)?)?;
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+/// a [`DatabaseRef`] that serves reads from a [`ForkCacheState`] and only falls
+/// back to `DB` on a miss, recording the result. without a state every read
+/// goes to `DB`
+#[derive(Debug)]
+pub struct ForkCacheDb<DB> {
+    inner: DB,
+    state: Option<Arc<ForkCacheState>>
+}
+
+impl<DB> ForkCacheDb<DB> {
+    pub fn new(inner: DB, state: Option<Arc<ForkCacheState>>) -> Self {
+        Self { inner, state }
+    }
+
+    pub fn state(&self) -> Option<&Arc<ForkCacheState>> {
+        self.state.as_ref()
+    }
+
+    pub fn inner(&self) -> &DB {
+        &self.inner
+    }
+}
+
+impl<DB> Drop for ForkCacheDb<DB> {
+    fn drop(&mut self) {
+        if let Some(state) = &self.state
+            && let Err(e) = state.flush()
+        {
+            tracing::warn!(path = %state.path().display(), "failed to flush the fork cache: {e}");
+        }
+    }
+}
+
+impl<DB: DatabaseRef> DatabaseRef for ForkCacheDb<DB> {
+    type Error = DB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let Some(state) = &self.state else { return self.inner.basic_ref(address) };
+        if let Some(account) = state.get(|e| e.accounts.get(&address).cloned()) {
+            return Ok(account.map(|mut info| {
+                info.code = state.get(|e| e.contracts.get(&info.code_hash).cloned());
+                info
+            }));
+        }
+
+        let account = self.inner.basic_ref(address)?;
+        state.insert(|e| {
+            // the code is kept once, under its hash
+            let mut stored = account.clone();
+            if let Some(info) = stored.as_mut()
+                && let Some(code) = info.code.take()
+            {
+                e.contracts.insert(info.code_hash, code);
+            }
+            e.accounts.insert(address, stored);
+        });
+
+        Ok(account)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        let Some(state) = &self.state else { return self.inner.code_by_hash_ref(code_hash) };
+        if let Some(code) = state.get(|e| e.contracts.get(&code_hash).cloned()) {
+            return Ok(code);
+        }
+
+        let code = self.inner.code_by_hash_ref(code_hash)?;
+        state.insert(|e| {
+            e.contracts.insert(code_hash, code.clone());
+        });
+
+        Ok(code)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let Some(state) = &self.state else { return self.inner.storage_ref(address, index) };
+        if let Some(value) = state.get(|e| e.storage.get(&address)?.get(&index).copied()) {
+            return Ok(value);
+        }
+
+        let value = self.inner.storage_ref(address, index)?;
+        state.insert(|e| {
+            e.storage.entry(address).or_default().insert(index, value);
+        });
+
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        let Some(state) = &self.state else { return self.inner.block_hash_ref(number) };
+        if let Some(hash) = state.get(|e| e.block_hashes.get(&number).copied()) {
+            return Ok(hash);
+        }
+
+        let hash = self.inner.block_hash_ref(number)?;
+        state.insert(|e| {
+            e.block_hashes.insert(number, hash);
+        });
+
+        Ok(hash)
+    }
+}
+
+/// wraps an [`EthRevm`] so that every read at a pinned block is persisted
+/// under `dir/<chain id>/<block hash>.json`. a block number is resolved to its
+/// hash through the inner database the first time it is seen and the hash is
+/// kept in `dir/<chain id>/block_hashes.json`, so after the first run
+/// simulations at the same block never reach the inner database. pin block
+/// numbers that can no longer reorg, or pin by hash. tags can move, so they
+/// are not cached
+#[derive(Debug)]
+pub struct ForkCache<T> {
+    inner:  T,
+    dir:    PathBuf,
+    hashes: Mutex<HashMap<ChainId, HashMap<u64, B256>>>,
+    states: Mutex<HashMap<(ChainId, B256), Arc<ForkCacheState>>>
+}
+
+impl<T> ForkCache<T> {
+    pub fn new(inner: T, dir: impl Into<PathBuf>) -> Self {
+        Self { inner, dir: dir.into(), hashes: Mutex::new(HashMap::new()), states: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn block_hashes_path(&self, chain_id: ChainId) -> PathBuf {
+        self.dir
+            .join(chain_id.to_string())
+            .join("block_hashes.json")
+    }
+
+    /// writes every loaded cache to disk
+    pub fn flush(&self) -> eyre::Result<()> {
+        self.states
+            .lock()
+            .map_err(|e| eyre!("{e}"))?
+            .values()
+            .try_for_each(|state| state.flush())
+    }
+}
+
+impl<T: EthRevm> ForkCache<T> {
+    /// the shared cache of the block `params` is pinned to, loaded from disk on
+    /// first use. `None` if `params` is pinned to a tag
+    pub fn state(&self, params: &T::Params) -> eyre::Result<Option<Arc<ForkCacheState>>> {
+        self.state_with(params, None)
+    }
+
+    fn state_with(&self, params: &T::Params, inner: Option<&T::InnerDb>) -> eyre::Result<Option<Arc<ForkCacheState>>> {
+        let Some(block_hash) = self.block_hash(params, inner)? else { return Ok(None) };
+
+        let mut states = self.states.lock().map_err(|e| eyre!("{e}"))?;
+        if let Some(state) = states.get(&(params.chain_id(), block_hash)) {
+            return Ok(Some(state.clone()));
+        }
+
+        let state = Arc::new(ForkCacheState::load(&self.dir, params.chain_id(), block_hash)?);
+        states.insert((params.chain_id(), block_hash), state.clone());
+
+        Ok(Some(state))
+    }
+
+    /// the hash of the block `params` is pinned to, looked up with `inner` (or
+    /// a fresh inner database) the first time a block number is seen on disk
+    fn block_hash(&self, params: &T::Params, inner: Option<&T::InnerDb>) -> eyre::Result<Option<B256>> {
+        let block_number = match params.block_id() {
+            BlockId::Hash(hash) => return Ok(Some(hash.block_hash)),
+            BlockId::Number(BlockNumberOrTag::Number(block_number)) => block_number,
+            BlockId::Number(_) => return Ok(None)
+        };
+
+        let chain_id = params.chain_id();
+        let path = self.block_hashes_path(chain_id);
+        {
+            let mut hashes = self.hashes.lock().map_err(|e| eyre!("{e}"))?;
+            let chain_hashes = match hashes.entry(chain_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(read_json(&path)?)
+            };
+            if let Some(hash) = chain_hashes.get(&block_number) {
+                return Ok(Some(*hash));
+            }
+        }
+
+        let hash = match inner {
+            Some(db) => db.block_hash_ref(block_number),
+            None => self.inner.make_inner_db(params)?.block_hash_ref(block_number)
+        }
+        .map_err(|e| eyre!("{e}"))?;
+
+        let mut hashes = self.hashes.lock().map_err(|e| eyre!("{e}"))?;
+        let chain_hashes = hashes.entry(chain_id).or_default();
+        chain_hashes.insert(block_number, hash);
+        write_json(&path, chain_hashes)?;
+
+        Ok(Some(hash))
+    }
+}
+
+impl<T: EthRevm> EthRevm for ForkCache<T> {
+    type InnerDb = ForkCacheDb<T::InnerDb>;
+    type Params = T::Params;
+
+    fn make_inner_db(&self, params: &Self::Params) -> eyre::Result<Self::InnerDb> {
+        let inner = self.inner.make_inner_db(params)?;
+        let state = self.state_with(params, Some(&inner))?;
+        Ok(ForkCacheDb::new(inner, state))
+    }
+
+    fn make_block_env(&self, params: &Self::Params) -> eyre::Result<BlockEnv> {
+        let Some(state) = self.state(params)? else {
+            return self.inner.make_block_env(params);
+        };
+        if let Some(block_env) = state.get(|e| e.block_env.clone()) {
+            return Ok(block_env);
+        }
+
+        let block_env = self.inner.make_block_env(params)?;
+        state.insert(|e| e.block_env = Some(block_env.clone()));
+
+        Ok(block_env)
+    }
+
+    fn make_cfg_env(&self, params: &Self::Params, block_env: &BlockEnv) -> eyre::Result<CfgEnv> {
+        self.inner.make_cfg_env(params, block_env)
+    }
+
+    #[cfg(feature = "op-revm")]
+    fn make_op_spec(&self, params: &Self::Params, block_env: &BlockEnv) -> eyre::Result<op_revm::OpSpecId> {
+        self.inner.make_op_spec(params, block_env)
+    }
+}
+
+impl<T> Drop for ForkCache<T> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::warn!(dir = %self.dir.display(), "failed to flush the fork cache: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicUsize, Ordering}
+    };
+
+    use alloy_primitives::address;
+    use revm::context_interface::DBErrorMarker;
+
+    use super::*;
+    use crate::traits::EthRevmParams;
+
+    #[derive(Default)]
+    struct CountingDb(AtomicUsize);
+
+    impl DatabaseRef for CountingDb {
+        type Error = Infallible;
+
+        fn basic_ref(&self, _: Address) -> Result<Option<AccountInfo>, Self::Error> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(Some(AccountInfo::from_bytecode(Bytecode::new_raw(vec![0x60, 0x00].into()))))
+        }
+
+        fn code_by_hash_ref(&self, _: B256) -> Result<Bytecode, Self::Error> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(Bytecode::default())
+        }
+
+        fn storage_ref(&self, _: Address, index: U256) -> Result<U256, Self::Error> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(index + U256::from(1))
+        }
+
+        fn block_hash_ref(&self, _: u64) -> Result<B256, Self::Error> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(B256::repeat_byte(1))
+        }
+    }
+
+    #[test]
+    fn test_fork_cache_persists() {
+        let dir = std::env::temp_dir().join(format!("lib-reth-fork-cache-{}", std::process::id()));
+        let account = address!("0x0000000000000000000000000000000000000001");
+
+        let block_hash = B256::repeat_byte(2);
+
+        let state = Arc::new(ForkCacheState::load(&dir, 1, block_hash).unwrap());
+        let db = ForkCacheDb::new(CountingDb::default(), Some(state.clone()));
+        let info = db.basic_ref(account).unwrap().unwrap();
+        assert_eq!(db.storage_ref(account, U256::from(1)).unwrap(), U256::from(2));
+        assert_eq!(db.block_hash_ref(99).unwrap(), B256::repeat_byte(1));
+        assert_eq!(db.inner().0.load(Ordering::Relaxed), 3);
+        drop(db);
+
+        let db = ForkCacheDb::new(CountingDb::default(), Some(Arc::new(ForkCacheState::load(&dir, 1, block_hash).unwrap())));
+        assert_eq!(db.basic_ref(account).unwrap().unwrap(), info);
+        assert_eq!(db.code_by_hash_ref(info.code_hash).unwrap(), info.code.unwrap());
+        assert_eq!(db.storage_ref(account, U256::from(1)).unwrap(), U256::from(2));
+        assert_eq!(db.block_hash_ref(99).unwrap(), B256::repeat_byte(1));
+        assert_eq!(db.inner().0.load(Ordering::Relaxed), 0);
+
+        // a reorged block at the same height has a cache of its own
+        let db = ForkCacheDb::new(
+            CountingDb::default(),
+            Some(Arc::new(ForkCacheState::load(&dir, 1, B256::repeat_byte(3)).unwrap()))
+        );
+        db.basic_ref(account).unwrap();
+        assert_eq!(db.inner().0.load(Ordering::Relaxed), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[derive(Debug)]
+    struct Offline;
+
+    impl std::fmt::Display for Offline {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "offline")
+        }
+    }
+
+    impl std::error::Error for Offline {}
+
+    impl DBErrorMarker for Offline {}
+
+    /// a remote node that errors on every call once `online` is false
+    struct Remote {
+        online: bool
+    }
+
+    impl DatabaseRef for Remote {
+        type Error = Offline;
+
+        fn basic_ref(&self, _: Address) -> Result<Option<AccountInfo>, Self::Error> {
+            self.online.then(AccountInfo::default).map(Some).ok_or(Offline)
+        }
+
+        fn code_by_hash_ref(&self, _: B256) -> Result<Bytecode, Self::Error> {
+            self.online.then(Bytecode::default).ok_or(Offline)
+        }
+
+        fn storage_ref(&self, _: Address, index: U256) -> Result<U256, Self::Error> {
+            self.online.then_some(index + U256::from(1)).ok_or(Offline)
+        }
+
+        fn block_hash_ref(&self, _: u64) -> Result<B256, Self::Error> {
+            self.online.then_some(B256::repeat_byte(2)).ok_or(Offline)
+        }
+    }
+
+    impl EthRevm for Remote {
+        type InnerDb = Remote;
+        type Params = EthRevmParams;
+
+        fn make_inner_db(&self, _: &EthRevmParams) -> eyre::Result<Remote> {
+            Ok(Remote { online: self.online })
+        }
+
+        fn make_block_env(&self, _: &EthRevmParams) -> eyre::Result<BlockEnv> {
+            eyre::ensure!(self.online, "offline");
+            Ok(BlockEnv { number: U256::from(99), ..Default::default() })
+        }
+    }
+
+    #[test]
+    fn test_fork_cache_reopens_offline() {
+        let dir = std::env::temp_dir().join(format!("lib-reth-fork-cache-offline-{}", std::process::id()));
+        let account = address!("0x0000000000000000000000000000000000000001");
+        let params = EthRevmParams { block_id: BlockId::number(99), chain_id: 1 };
+
+        let cache = ForkCache::new(Remote { online: true }, &dir);
+        let db = cache.make_inner_db(&params).unwrap();
+        assert_eq!(db.storage_ref(account, U256::from(1)).unwrap(), U256::from(2));
+        assert_eq!(cache.make_block_env(&params).unwrap().number, U256::from(99));
+        drop(db);
+        drop(cache);
+
+        let cache = ForkCache::new(Remote { online: false }, &dir);
+        let db = cache.make_inner_db(&params).unwrap();
+        assert_eq!(db.storage_ref(account, U256::from(1)).unwrap(), U256::from(2));
+        assert!(db.storage_ref(account, U256::from(2)).is_err());
+        assert_eq!(cache.make_block_env(&params).unwrap().number, U256::from(99));
+        assert!(
+            cache
+                .make_inner_db(&EthRevmParams { block_id: BlockId::number(100), chain_id: 1 })
+                .is_err()
+        );
+        drop(db);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,105 @@
+use alloy_primitives::{Address, Bytes, TxKind, address};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+use revm::{DatabaseCommit, DatabaseRef, context_interface::ContextTr, handler::EvmTr};
+
+use crate::traits::{MainnetRevmEvm, SimulationOutcome, execute_mainnet_evm};
+
+/// the sender of lens deployments and calls. it has no key or balance, so the
+/// evm's balance check is off for lens runs
+pub const LENS_CALLER: Address = address!("0x1e45000000000000000000000000000000001e45");
+
+/// deploys `creation_code` (constructor arguments appended, if any) on `evm`
+/// and calls `call` on the deployed contract, decoding its return. the
+/// deployment and the call are committed to the evm's `CacheDB`. both runs
+/// get the block gas limit, capped at the tx gas cap of the spec
+pub fn call_lens_mainnet_evm<DB, C>(evm: &mut MainnetRevmEvm<DB>, creation_code: Bytes, call: &C) -> eyre::Result<C::Return>
+where
+    DB: DatabaseRef,
+    C: SolCall
+{
+    let lens = deploy_lens_mainnet_evm(evm, creation_code)?;
+    let outcome = run_lens(evm, TransactionRequest::default().to(lens).input(call.abi_encode().into()), "call")?;
+
+    C::abi_decode_returns(&outcome.output).map_err(|e| eyre::eyre!("failed to decode the lens return: {e}"))
+}
+
+/// deploys `creation_code` on `evm` from [`LENS_CALLER`] and returns the
+/// address of the contract
+pub fn deploy_lens_mainnet_evm<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    creation_code: Bytes
+) -> eyre::Result<Address> {
+    let nonce = evm
+        .ctx_ref()
+        .db_ref()
+        .basic_ref(LENS_CALLER)
+        .map_err(|e| eyre::eyre!("{e:?}"))?
+        .map(|info| info.nonce)
+        .unwrap_or_default();
+
+    let mut tx = TransactionRequest::default().input(creation_code.into());
+    tx.to = Some(TxKind::Create);
+    run_lens(evm, tx, "deployment")?;
+
+    Ok(LENS_CALLER.create(nonce))
+}
+
+/// runs `tx` from [`LENS_CALLER`] and commits it. the balance check is off for
+/// the run, and so are the code size limits for a deployment, as lenses never
+/// go on chain. the evm's cfg is restored afterwards
+fn run_lens<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    tx: TransactionRequest,
+    what: &str
+) -> eyre::Result<SimulationOutcome> {
+    let cfg = evm.ctx_ref().cfg.clone();
+    let is_deployment = tx.to == Some(TxKind::Create);
+    evm.ctx_mut().modify_cfg(|cfg| {
+        cfg.disable_balance_check = true;
+        if is_deployment {
+            cfg.limit_contract_code_size = Some(usize::MAX);
+            cfg.limit_contract_initcode_size = Some(usize::MAX);
+        }
+    });
+
+    let result = execute_mainnet_evm(evm, tx.from(LENS_CALLER));
+    evm.ctx_mut().modify_cfg(|c| *c = cfg);
+
+    let (outcome, state) = result?;
+    if !outcome.is_success() {
+        eyre::bail!("lens {what} failed: {:?}", outcome.status);
+    }
+    evm.ctx_mut().db_mut().commit(state);
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{U256, hex};
+    use revm_database::{CacheDB, EmptyDB};
+
+    use super::*;
+    use crate::traits::empty_mainnet_revm;
+
+    alloy_sol_types::sol! {
+        function value() external returns (uint256);
+    }
+
+    #[test]
+    fn test_call_lens() {
+        // copies the 10 byte runtime after it, which returns 42 to any call
+        let creation_code = hex!("600a600c600039600a6000f3602a60005260206000f3");
+        let mut evm = empty_mainnet_revm(CacheDB::new(EmptyDB::default()), 1, false);
+        evm.ctx_mut()
+            .modify_cfg(|cfg| cfg.limit_contract_code_size = Some(5));
+
+        let value = call_lens_mainnet_evm(&mut evm, creation_code.into(), &valueCall {}).unwrap();
+        assert_eq!(value, U256::from(42));
+
+        // the lens runs leave the cfg as it was
+        assert!(!evm.ctx_ref().cfg.disable_balance_check);
+        assert_eq!(evm.ctx_ref().cfg.limit_contract_code_size, Some(5));
+    }
+}
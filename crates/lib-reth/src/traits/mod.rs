@@ -6,5 +6,55 @@ mod revm;
 #[cfg(feature = "revm")]
 pub use revm::*;
 
+#[cfg(feature = "revm")]
+mod simulation;
+#[cfg(feature = "revm")]
+pub use simulation::*;
+
+#[cfg(feature = "revm")]
+mod overrides;
+#[cfg(feature = "revm")]
+pub use overrides::*;
+
+#[cfg(feature = "revm")]
+mod tracers;
+#[cfg(feature = "revm")]
+pub use tracers::*;
+
+#[cfg(feature = "revm")]
+mod estimation;
+#[cfg(feature = "revm")]
+pub use estimation::*;
+
+#[cfg(feature = "revm")]
+mod bundle;
+#[cfg(feature = "revm")]
+pub use bundle::*;
+
+#[cfg(feature = "revm")]
+mod lens;
+#[cfg(feature = "revm")]
+pub use lens::*;
+
+#[cfg(feature = "revm")]
+mod multi_block;
+#[cfg(feature = "revm")]
+pub use multi_block::*;
+
+#[cfg(feature = "revm")]
+mod checkpoint;
+#[cfg(feature = "revm")]
+pub use checkpoint::*;
+
+#[cfg(feature = "revm")]
+mod prefetch;
+#[cfg(feature = "revm")]
+pub use prefetch::*;
+
+#[cfg(feature = "fork-cache")]
+mod fork_cache;
+#[cfg(feature = "fork-cache")]
+pub use fork_cache::*;
+
 #[cfg(all(feature = "revm", feature = "reth-db"))]
 pub mod reth_revm_utils;
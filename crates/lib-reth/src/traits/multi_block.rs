@@ -0,0 +1,231 @@
+use alloy_eips::eip1559::{BaseFeeParams, calc_next_block_base_fee};
+use alloy_primitives::{B256, U256, keccak256};
+use alloy_rpc_types::BlockOverrides;
+use revm::{DatabaseRef, context::BlockEnv, context_interface::ContextTr, handler::EvmTr};
+use revm_database::CacheDB;
+
+use crate::traits::{BundleOutcome, BundleTransaction, MainnetRevmEvm, apply_block_overrides, simulate_mainnet_bundle};
+
+/// how the synthetic blocks after the first are derived from the one before
+#[derive(Debug, Clone, Copy)]
+pub struct BlockProgression {
+    /// seconds between blocks
+    pub block_time:      u64,
+    /// prices each block's base fee from the gas its parent used
+    pub base_fee_params: BaseFeeParams
+}
+
+impl BlockProgression {
+    pub fn ethereum() -> Self {
+        Self { block_time: 12, base_fee_params: BaseFeeParams::ethereum() }
+    }
+}
+
+/// a group of transactions executed as one synthetic block
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedBlock {
+    pub txs:       Vec<BundleTransaction>,
+    /// if set, the first failed transaction discards the effects of the
+    /// whole block. see [`simulate_mainnet_bundle`]
+    pub atomic:    bool,
+    /// applied on top of the derived block env. overridden values carry over
+    /// to the blocks after it
+    pub overrides: Option<BlockOverrides>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedBlockOutcome {
+    /// the env the block was executed in, overrides included
+    pub block_env: BlockEnv,
+    pub bundle:    BundleOutcome
+}
+
+/// the evms blocks can be simulated on
+trait BlockSimulationEvm {
+    type Db: DatabaseRef;
+
+    fn block_env(&self) -> BlockEnv;
+
+    fn set_block_env(&mut self, block_env: BlockEnv);
+
+    fn cache_db(&mut self) -> &mut CacheDB<Self::Db>;
+
+    fn simulate_bundle(&mut self, txs: Vec<BundleTransaction>, atomic: bool) -> eyre::Result<BundleOutcome>;
+}
+
+impl<DB: DatabaseRef> BlockSimulationEvm for MainnetRevmEvm<DB> {
+    type Db = DB;
+
+    fn block_env(&self) -> BlockEnv {
+        self.ctx_ref().block.clone()
+    }
+
+    fn set_block_env(&mut self, block_env: BlockEnv) {
+        self.ctx_mut().modify_block(|block| *block = block_env);
+    }
+
+    fn cache_db(&mut self) -> &mut CacheDB<DB> {
+        self.ctx_mut().db_mut()
+    }
+
+    fn simulate_bundle(&mut self, txs: Vec<BundleTransaction>, atomic: bool) -> eyre::Result<BundleOutcome> {
+        simulate_mainnet_bundle(self, txs, atomic)
+    }
+}
+
+/// simulates `blocks` in order on `evm`, each on top of the state the ones
+/// before it left. the first block is executed in the evm's block env; every
+/// later one is the block after it, `progression.block_time` seconds later,
+/// with its base fee updated by eip-1559 from the gas the block before it used.
+/// `BLOCKHASH` of a later block's parent is [`synthetic_block_hash`], unless
+/// the hash was overridden
+pub fn simulate_mainnet_blocks<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    blocks: Vec<SimulatedBlock>,
+    progression: BlockProgression
+) -> eyre::Result<Vec<SimulatedBlockOutcome>> {
+    simulate_blocks_with(evm, blocks, progression)
+}
+
+fn simulate_blocks_with<E: BlockSimulationEvm>(
+    evm: &mut E,
+    blocks: Vec<SimulatedBlock>,
+    progression: BlockProgression
+) -> eyre::Result<Vec<SimulatedBlockOutcome>> {
+    let mut outcomes: Vec<SimulatedBlockOutcome> = Vec::with_capacity(blocks.len());
+
+    for SimulatedBlock { txs, atomic, overrides } in blocks {
+        let mut block_env = evm.block_env();
+
+        if let Some(parent) = outcomes.last() {
+            block_env = next_synthetic_block_env(&parent.block_env, parent.bundle.gas_used, progression);
+
+            // the first block's hash is known to the database, the ones after
+            // it never existed
+            if outcomes.len() > 1 {
+                evm.cache_db()
+                    .cache
+                    .block_hashes
+                    .entry(parent.block_env.number)
+                    .or_insert_with(|| synthetic_block_hash(parent.block_env.number));
+            }
+        }
+        if let Some(overrides) = &overrides {
+            apply_block_overrides(&mut block_env, evm.cache_db(), overrides);
+        }
+        evm.set_block_env(block_env.clone());
+
+        let bundle = evm.simulate_bundle(txs, atomic)?;
+        outcomes.push(SimulatedBlockOutcome { block_env, bundle });
+    }
+
+    Ok(outcomes)
+}
+
+/// the hash a synthetic block is given, the keccak of its number
+pub fn synthetic_block_hash(number: U256) -> B256 {
+    keccak256(number.to_be_bytes::<32>())
+}
+
+/// the block after `parent`. the randomness of a block can't be known ahead of
+/// it, so `prevrandao` is the keccak of the parent's. blob gas isn't tracked,
+/// so the blob excess gas and price of the parent carry over unchanged
+fn next_synthetic_block_env(parent: &BlockEnv, parent_gas_used: u64, progression: BlockProgression) -> BlockEnv {
+    BlockEnv {
+        number: parent.number + U256::from(1),
+        timestamp: parent.timestamp + U256::from(progression.block_time),
+        basefee: calc_next_block_base_fee(
+            parent_gas_used,
+            parent.gas_limit,
+            parent.basefee,
+            progression.base_fee_params
+        ),
+        prevrandao: parent.prevrandao.map(keccak256),
+        ..parent.clone()
+    }
+}
+
+#[cfg(feature = "op-revm")]
+pub use op_impl::simulate_op_blocks;
+
+#[cfg(feature = "op-revm")]
+mod op_impl {
+    use super::*;
+    use crate::traits::{OptimismRevmEvm, simulate_op_bundle};
+
+    impl<DB: DatabaseRef> BlockSimulationEvm for OptimismRevmEvm<DB> {
+        type Db = DB;
+
+        fn block_env(&self) -> BlockEnv {
+            self.ctx_ref().block.clone()
+        }
+
+        fn set_block_env(&mut self, block_env: BlockEnv) {
+            self.ctx_mut().modify_block(|block| *block = block_env);
+        }
+
+        fn cache_db(&mut self) -> &mut CacheDB<DB> {
+            self.ctx_mut().db_mut()
+        }
+
+        fn simulate_bundle(&mut self, txs: Vec<BundleTransaction>, atomic: bool) -> eyre::Result<BundleOutcome> {
+            simulate_op_bundle(self, txs, atomic)
+        }
+    }
+
+    /// [`simulate_mainnet_blocks`] on the op evm
+    pub fn simulate_op_blocks<DB: DatabaseRef>(
+        evm: &mut OptimismRevmEvm<DB>,
+        blocks: Vec<SimulatedBlock>,
+        progression: BlockProgression
+    ) -> eyre::Result<Vec<SimulatedBlockOutcome>> {
+        simulate_blocks_with(evm, blocks, progression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use revm_database::EmptyDB;
+
+    use super::*;
+    use crate::traits::empty_mainnet_revm;
+
+    #[test]
+    fn test_next_synthetic_block_env() {
+        let parent = BlockEnv {
+            number: U256::from(100),
+            timestamp: U256::from(1_000),
+            gas_limit: 30_000_000,
+            basefee: 1_000_000_000,
+            prevrandao: Some(B256::repeat_byte(1)),
+            ..Default::default()
+        };
+
+        let full = next_synthetic_block_env(&parent, 30_000_000, BlockProgression::ethereum());
+        assert_eq!(full.number, U256::from(101));
+        assert_eq!(full.timestamp, U256::from(1_012));
+        assert_eq!(full.basefee, 1_125_000_000);
+        assert_eq!(full.gas_limit, parent.gas_limit);
+        assert_eq!(full.prevrandao, Some(keccak256(B256::repeat_byte(1))));
+
+        let target = next_synthetic_block_env(&parent, 15_000_000, BlockProgression::ethereum());
+        assert_eq!(target.basefee, parent.basefee);
+
+        let empty = next_synthetic_block_env(&parent, 0, BlockProgression::ethereum());
+        assert_eq!(empty.basefee, 875_000_000);
+    }
+
+    #[test]
+    fn test_synthetic_block_hashes() {
+        let mut evm = empty_mainnet_revm(CacheDB::new(EmptyDB::default()), 1, false);
+        evm.ctx_mut()
+            .modify_block(|block| block.number = U256::from(100));
+
+        simulate_mainnet_blocks(&mut evm, vec![SimulatedBlock::default(); 3], BlockProgression::ethereum()).unwrap();
+
+        let block_hashes = &evm.ctx_ref().db_ref().cache.block_hashes;
+        assert!(!block_hashes.contains_key(&U256::from(100)));
+        assert_eq!(block_hashes.get(&U256::from(101)), Some(&synthetic_block_hash(U256::from(101))));
+        assert!(!block_hashes.contains_key(&U256::from(102)));
+    }
+}
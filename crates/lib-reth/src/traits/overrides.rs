@@ -0,0 +1,234 @@
+use alloy_primitives::{B256, U256};
+use alloy_rpc_types::{BlockOverrides, state::StateOverride};
+use revm::{
+    DatabaseRef,
+    context::BlockEnv,
+    state::{AccountInfo, Bytecode}
+};
+use revm_database::CacheDB;
+
+/// writes `overrides` into the cache of `db`. the inner database is never
+/// written to
+pub fn apply_state_overrides<DB: DatabaseRef>(db: &mut CacheDB<DB>, overrides: &StateOverride) -> eyre::Result<()> {
+    for (address, account) in overrides {
+        if account.move_precompile_to.is_some() {
+            eyre::bail!("moving precompiles is not supported, requested for {address}");
+        }
+        if account.state.is_some() && account.state_diff.is_some() {
+            eyre::bail!("both state and stateDiff are set for {address}");
+        }
+
+        let mut info = db
+            .basic_ref(*address)
+            .map_err(|e| eyre::eyre!("{e:?}"))?
+            .unwrap_or_else(AccountInfo::default);
+        if let Some(balance) = account.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = account.nonce {
+            info.nonce = nonce;
+        }
+        if let Some(code) = &account.code {
+            let code = Bytecode::new_raw(code.clone());
+            info.code_hash = code.hash_slow();
+            info.code = Some(code);
+        }
+        db.insert_account_info(*address, info);
+
+        if let Some(state) = &account.state {
+            let storage = state
+                .iter()
+                .map(|(slot, value)| (slot_to_u256(*slot), slot_to_u256(*value)))
+                .collect();
+            db.replace_account_storage(*address, storage)
+                .map_err(|e| eyre::eyre!("{e:?}"))?;
+        }
+
+        if let Some(state_diff) = &account.state_diff {
+            for (slot, value) in state_diff {
+                db.insert_account_storage(*address, slot_to_u256(*slot), slot_to_u256(*value))
+                    .map_err(|e| eyre::eyre!("{e:?}"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// writes `overrides` into `block_env`. overridden block hashes go into the
+/// cache of `db`
+pub fn apply_block_overrides<DB: DatabaseRef>(
+    block_env: &mut BlockEnv,
+    db: &mut CacheDB<DB>,
+    overrides: &BlockOverrides
+) {
+    if let Some(number) = overrides.number {
+        block_env.number = number;
+    }
+    if let Some(difficulty) = overrides.difficulty {
+        block_env.difficulty = difficulty;
+    }
+    if let Some(time) = overrides.time {
+        block_env.timestamp = U256::from(time);
+    }
+    if let Some(gas_limit) = overrides.gas_limit {
+        block_env.gas_limit = gas_limit;
+    }
+    if let Some(coinbase) = overrides.coinbase {
+        block_env.beneficiary = coinbase;
+    }
+    if let Some(random) = overrides.random {
+        block_env.prevrandao = Some(random);
+    }
+    if let Some(base_fee) = overrides.base_fee {
+        block_env.basefee = base_fee.saturating_to();
+    }
+    if let Some(block_hashes) = &overrides.block_hash {
+        db.cache.block_hashes.extend(
+            block_hashes
+                .iter()
+                .map(|(number, hash)| (U256::from(*number), *hash))
+        );
+    }
+}
+
+fn slot_to_u256(slot: B256) -> U256 {
+    U256::from_be_bytes(slot.0)
+}
+
+#[cfg(feature = "uniswap-storage")]
+pub use _uniswap_storage::StateOverrideFetcher;
+
+#[cfg(feature = "uniswap-storage")]
+mod _uniswap_storage {
+    use alloy_eips::BlockId;
+    use alloy_primitives::{Address, StorageKey, StorageValue};
+    use uniswap_storage::{StorageSlotFetcher, StorageSlotFetcherSync};
+
+    use super::*;
+
+    /// a [`StorageSlotFetcher`] that reads through `overrides` before
+    /// falling back to the inner fetcher, so storage reads see the same state
+    /// as an evm the overrides were applied to
+    #[derive(Debug, Clone)]
+    pub struct StateOverrideFetcher<F> {
+        inner:     F,
+        overrides: StateOverride
+    }
+
+    impl<F> StateOverrideFetcher<F> {
+        pub fn new(inner: F, overrides: StateOverride) -> Self {
+            Self { inner, overrides }
+        }
+
+        pub fn inner(&self) -> &F {
+            &self.inner
+        }
+
+        pub fn overrides(&self) -> &StateOverride {
+            &self.overrides
+        }
+
+        /// `None` if the slot is not overridden
+        fn overridden(&self, address: Address, key: StorageKey) -> Option<StorageValue> {
+            let account = self.overrides.get(&address)?;
+            if let Some(state) = &account.state {
+                return Some(state.get(&key).map(|v| slot_to_u256(*v)).unwrap_or_default());
+            }
+
+            account
+                .state_diff
+                .as_ref()?
+                .get(&key)
+                .map(|v| slot_to_u256(*v))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl<F: StorageSlotFetcher> StorageSlotFetcher for StateOverrideFetcher<F> {
+        async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+            match self.overridden(address, key) {
+                Some(value) => Ok(value),
+                None => self.inner.storage_at(address, key, block_id).await
+            }
+        }
+    }
+
+    impl<F: StorageSlotFetcherSync> StorageSlotFetcherSync for StateOverrideFetcher<F> {
+        fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+            match self.overridden(address, key) {
+                Some(value) => Ok(value),
+                None => self.inner.storage_at(address, key, block_id)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use alloy_primitives::{address, b256};
+        use alloy_rpc_types::state::AccountOverride;
+
+        use super::*;
+
+        struct ConstFetcher;
+
+        impl StorageSlotFetcherSync for ConstFetcher {
+            fn storage_at(&self, _: Address, _: StorageKey, _: BlockId) -> eyre::Result<StorageValue> {
+                Ok(StorageValue::from(7))
+            }
+        }
+
+        #[test]
+        fn test_state_override_fetcher() {
+            let replaced = address!("0x0000000000000000000000000000000000000001");
+            let diffed = address!("0x0000000000000000000000000000000000000002");
+            let slot = b256!("0x0000000000000000000000000000000000000000000000000000000000000001");
+            let value = b256!("0x0000000000000000000000000000000000000000000000000000000000000005");
+
+            let mut overrides = StateOverride::default();
+            overrides.insert(replaced, AccountOverride::default().with_state([(slot, value)]));
+            overrides.insert(diffed, AccountOverride::default().with_state_diff([(slot, value)]));
+            let fetcher = StateOverrideFetcher::new(ConstFetcher, overrides);
+
+            let read = |address, key| StorageSlotFetcherSync::storage_at(&fetcher, address, key, BlockId::latest()).unwrap();
+            assert_eq!(read(replaced, slot), StorageValue::from(5));
+            assert_eq!(read(replaced, B256::ZERO), StorageValue::ZERO);
+            assert_eq!(read(diffed, slot), StorageValue::from(5));
+            assert_eq!(read(diffed, B256::ZERO), StorageValue::from(7));
+            assert_eq!(read(Address::ZERO, slot), StorageValue::from(7));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "reth-db", not(feature = "ci")))]
+mod tests {
+    use alloy_eips::BlockId;
+    use alloy_primitives::{U256, address};
+    use alloy_rpc_types::state::{AccountOverride, EvmOverrides, StateOverride};
+
+    use crate::{
+        test_utils::{mainnet_client, weth_deposit_tx},
+        traits::{EthRevm, EthRevmParams}
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_simulate_tx_with_overrides() {
+        let client = mainnet_client();
+        let params = EthRevmParams { block_id: BlockId::latest(), chain_id: 1 };
+
+        // WETH.deposit() from an account without any eth
+        let caller = address!("0x00000000000000000000000000000000000beef1");
+        let tx = weth_deposit_tx().from(caller);
+        assert!(client.simulate_tx(&params, tx.clone()).is_err());
+
+        let mut state = StateOverride::default();
+        state.insert(caller, AccountOverride::default().with_balance(U256::from(10).pow(U256::from(19))));
+        let overrides = EvmOverrides::state(Some(state));
+
+        let outcome = client
+            .simulate_tx_with_overrides(&params, tx, &overrides)
+            .unwrap();
+        assert!(outcome.is_success());
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    sync::{Mutex, PoisonError}
+};
+
+use alloy_eips::BlockId;
+use alloy_network::{BlockResponse, Network, primitives::HeaderResponse};
+use alloy_primitives::{Address, B256, U256};
+use alloy_provider::Provider;
+use alloy_rpc_types::TransactionRequest;
+use futures::{StreamExt, TryStreamExt};
+use revm::{
+    Context, DatabaseCommit, DatabaseRef, MainBuilder, MainContext,
+    context::{BlockEnv, CfgEnv},
+    context_interface::ContextTr,
+    handler::EvmTr,
+    state::{AccountInfo, Bytecode}
+};
+use revm_database::{Cache, CacheDB};
+
+use crate::traits::execute_mainnet_evm;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrefetchConfig {
+    /// the most requests in flight at once
+    pub max_concurrency: usize,
+    /// the most tracing passes. every pass after the first only runs if the
+    /// one before it found keys that were not fetched yet
+    pub max_rounds:      usize
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self { max_concurrency: 64, max_rounds: 8 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PrefetchKey {
+    Account(Address),
+    Slot(Address, U256),
+    BlockHash(u64)
+}
+
+enum Prefetched {
+    Account(Address, AccountInfo),
+    Slot(Address, U256, U256),
+    BlockHash(u64, B256)
+}
+
+/// a [`DatabaseRef`] that never fetches. every read is recorded and answered
+/// with an empty value, so a tracing pass over it finds the keys a transaction
+/// needs without waiting on any of them
+#[derive(Debug, Default)]
+struct MissRecorder(Mutex<HashSet<PrefetchKey>>);
+
+// a key is inserted whole or not at all, so a poisoned set is still sound
+impl MissRecorder {
+    fn record(&self, key: PrefetchKey) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key);
+    }
+
+    fn into_keys(self) -> HashSet<PrefetchKey> {
+        self.0
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl DatabaseRef for MissRecorder {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.record(PrefetchKey::Account(address));
+        Ok(None)
+    }
+
+    fn code_by_hash_ref(&self, _: B256) -> Result<Bytecode, Self::Error> {
+        // the code is fetched with its account
+        Ok(Bytecode::default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.record(PrefetchKey::Slot(address, index));
+        Ok(U256::ZERO)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.record(PrefetchKey::BlockHash(number));
+        Ok(B256::ZERO)
+    }
+}
+
+/// fetches the state `txs` read at `block_id` concurrently, on top of `seed`,
+/// and returns `seed` with it loaded. `txs` are traced in order, each on top
+/// of the ones before it, with balance and nonce checks off; a pass that reads
+/// keys that were not fetched yet fetches them and traces again. the access
+/// lists of `txs` are fetched before the first pass
+pub async fn prefetch_state<P, N>(
+    provider: &P,
+    block_id: BlockId,
+    block_env: &BlockEnv,
+    cfg_env: &CfgEnv,
+    seed: Cache,
+    txs: &[TransactionRequest],
+    config: PrefetchConfig
+) -> eyre::Result<Cache>
+where
+    P: Provider<N>,
+    N: Network
+{
+    let mut cache = seed;
+    let mut keys = txs
+        .iter()
+        .flat_map(|tx| {
+            let accounts = tx
+                .from
+                .into_iter()
+                .chain(tx.to.and_then(|to| to.to().copied()))
+                .map(PrefetchKey::Account);
+            let access_list = tx.access_list.iter().flat_map(|list| {
+                list.iter().flat_map(|item| {
+                    std::iter::once(PrefetchKey::Account(item.address)).chain(
+                        item.storage_keys
+                            .iter()
+                            .map(|key| PrefetchKey::Slot(item.address, U256::from_be_bytes(key.0)))
+                    )
+                })
+            });
+            accounts.chain(access_list).collect::<Vec<_>>()
+        })
+        .filter(|key| !is_cached(&cache, key))
+        .collect::<HashSet<_>>();
+
+    for _ in 0..config.max_rounds {
+        if !keys.is_empty() {
+            fetch_into(provider, block_id, &mut cache, keys, config.max_concurrency).await?;
+        }
+
+        keys = trace_misses(block_env, cfg_env, &cache, txs)
+            .into_iter()
+            .filter(|key| !is_cached(&cache, key))
+            .collect();
+        if keys.is_empty() {
+            return Ok(cache);
+        }
+    }
+
+    // whatever is still missing is fetched by the inner database when the
+    // transactions run
+    Ok(cache)
+}
+
+/// the keys read by tracing `txs` over `cache` that it does not hold
+fn trace_misses(
+    block_env: &BlockEnv,
+    cfg_env: &CfgEnv,
+    cache: &Cache,
+    txs: &[TransactionRequest]
+) -> HashSet<PrefetchKey> {
+    let mut cfg_env = cfg_env.clone();
+    cfg_env.disable_balance_check = true;
+    cfg_env.disable_nonce_check = true;
+
+    let db = CacheDB { cache: cache.clone(), db: MissRecorder::default() };
+    let mut evm = Context::mainnet()
+        .with_block(block_env.clone())
+        .with_cfg(cfg_env)
+        .with_db(db)
+        .build_mainnet();
+
+    for tx in txs {
+        // a pass over missing state can fail in ways the real run does not.
+        // what it read before failing is still worth fetching
+        if let Ok((_, state)) = execute_mainnet_evm(&mut evm, tx.clone()) {
+            evm.ctx_mut().db_mut().commit(state);
+        }
+    }
+
+    std::mem::take(&mut evm.ctx_mut().db_mut().db).into_keys()
+}
+
+fn is_cached(cache: &Cache, key: &PrefetchKey) -> bool {
+    match key {
+        PrefetchKey::Account(address) => cache.accounts.contains_key(address),
+        PrefetchKey::Slot(address, slot) => cache
+            .accounts
+            .get(address)
+            .is_some_and(|account| account.storage.contains_key(slot)),
+        PrefetchKey::BlockHash(number) => cache.block_hashes.contains_key(&U256::from(*number))
+    }
+}
+
+async fn fetch_into<P, N>(
+    provider: &P,
+    block_id: BlockId,
+    cache: &mut Cache,
+    mut keys: HashSet<PrefetchKey>,
+    max_concurrency: usize
+) -> eyre::Result<()>
+where
+    P: Provider<N>,
+    N: Network
+{
+    // a slot is only cached under an account that is, or the account would
+    // read as empty
+    let accounts = keys
+        .iter()
+        .filter_map(|key| match key {
+            PrefetchKey::Slot(address, _) if !cache.accounts.contains_key(address) => {
+                Some(PrefetchKey::Account(*address))
+            }
+            _ => None
+        })
+        .collect::<Vec<_>>();
+    keys.extend(accounts);
+
+    let fetched = futures::stream::iter(keys)
+        .map(|key| fetch(provider, block_id, key))
+        .buffer_unordered(max_concurrency.max(1))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let (accounts, rest): (Vec<_>, Vec<_>) = fetched
+        .into_iter()
+        .partition(|fetched| matches!(fetched, Prefetched::Account(..)));
+
+    for fetched in accounts.into_iter().chain(rest) {
+        match fetched {
+            Prefetched::Account(address, info) => {
+                if let Some(code) = &info.code
+                    && !code.is_empty()
+                {
+                    cache.contracts.insert(info.code_hash, code.clone());
+                }
+                cache.accounts.entry(address).or_default().info = info;
+            }
+            Prefetched::Slot(address, slot, value) => {
+                if let Some(account) = cache.accounts.get_mut(&address) {
+                    account.storage.entry(slot).or_insert(value);
+                }
+            }
+            Prefetched::BlockHash(number, hash) => {
+                cache.block_hashes.insert(U256::from(number), hash);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch<P, N>(provider: &P, block_id: BlockId, key: PrefetchKey) -> eyre::Result<Prefetched>
+where
+    P: Provider<N>,
+    N: Network
+{
+    Ok(match key {
+        PrefetchKey::Account(address) => {
+            let (nonce, balance, code) = tokio::try_join!(
+                provider.get_transaction_count(address).block_id(block_id),
+                provider.get_balance(address).block_id(block_id),
+                provider.get_code_at(address).block_id(block_id)
+            )?;
+            let code = Bytecode::new_raw(code);
+
+            Prefetched::Account(address, AccountInfo::new(balance, nonce, code.hash_slow(), code))
+        }
+        PrefetchKey::Slot(address, slot) => {
+            let value = provider
+                .get_storage_at(address, slot)
+                .block_id(block_id)
+                .await?;
+
+            Prefetched::Slot(address, slot, value)
+        }
+        PrefetchKey::BlockHash(number) => {
+            let block = provider
+                .get_block_by_number(number.into())
+                .await?
+                .ok_or_else(|| eyre::eyre!("block {number} not found"))?;
+
+            Prefetched::BlockHash(number, block.header().hash())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+
+    use super::*;
+
+    #[test]
+    fn test_trace_misses() {
+        let from = address!("0x0000000000000000000000000000000000000001");
+        let to = address!("0x0000000000000000000000000000000000000002");
+        let tx = TransactionRequest::default().from(from).to(to);
+
+        let misses = trace_misses(&BlockEnv::default(), &CfgEnv::default(), &Cache::default(), &[tx.clone()]);
+        assert!(misses.contains(&PrefetchKey::Account(from)));
+        assert!(misses.contains(&PrefetchKey::Account(to)));
+
+        let mut cache = Cache::default();
+        cache.accounts.insert(from, Default::default());
+        cache.accounts.insert(to, Default::default());
+        let misses = trace_misses(&BlockEnv::default(), &CfgEnv::default(), &cache, &[tx]);
+        assert!(!misses.contains(&PrefetchKey::Account(from)));
+        assert!(!misses.contains(&PrefetchKey::Account(to)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prefetch_state() {
+        use alloy_network::Ethereum;
+        use alloy_primitives::{Bytes, hex};
+        use alloy_provider::RootProvider;
+
+        use crate::test_utils::mock_rpc_handler;
+
+        let from = address!("0x0000000000000000000000000000000000000001");
+        let to = address!("0x0000000000000000000000000000000000000002");
+        // PUSH1 5 SLOAD STOP
+        let code = hex!("60055400");
+
+        let (url, _requests) = mock_rpc_handler(move |body| {
+            let result = if body.contains("eth_getCode") {
+                if body.contains(&to.to_string().to_lowercase()) { hex::encode_prefixed(code) } else { "0x".to_string() }
+            } else if body.contains("eth_getStorageAt") {
+                "0x7".to_string()
+            } else {
+                "0x0".to_string()
+            };
+            format!("\"{result}\"")
+        })
+        .await
+        .unwrap();
+        let provider = RootProvider::<Ethereum>::new_http(url.parse().unwrap());
+
+        let tx = TransactionRequest::default().from(from).to(to);
+        let cache = prefetch_state(
+            &provider,
+            BlockId::latest(),
+            &BlockEnv::default(),
+            &CfgEnv::default(),
+            Cache::default(),
+            &[tx],
+            PrefetchConfig::default()
+        )
+        .await
+        .unwrap();
+
+        assert!(cache.accounts.contains_key(&from));
+        assert_eq!(cache.accounts[&to].info.code.as_ref().unwrap().original_bytes(), Bytes::copy_from_slice(&code));
+        assert_eq!(cache.accounts[&to].storage.get(&U256::from(5)), Some(&U256::from(7)));
+    }
+}
@@ -1,4 +1,7 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Arc, Condvar, Mutex, PoisonError}
+};
 
 use alloy_primitives::{Address, B256, U256};
 use reth_provider::StateProviderBox;
@@ -9,20 +12,166 @@ use revm::{
     state::{AccountInfo, Bytecode}
 };
 
+use crate::reth_libmdbx::PinnedBlock;
+
+type StateProviderOpener = dyn Fn() -> eyre::Result<StateProviderBox> + Send + Sync;
+
+/// a [`DatabaseRef`] over the state of one block. every read checks out its
+/// own state provider, so concurrent readers (e.g. rayon workers simulating
+/// against the same block) never hold a lock while reading
 #[derive(Clone)]
-pub struct RethLibmdbxDatabaseRef(Arc<Mutex<StateProviderDatabase<StateProviderBox>>>);
+pub struct RethLibmdbxDatabaseRef {
+    providers:    Arc<StateProviders>,
+    pinned_block: Option<PinnedBlock>
+}
+
+struct StateProviders {
+    /// opens a new provider when every existing one is checked out. without
+    /// it, reads error while every provider is checked out
+    open:          Option<Box<StateProviderOpener>>,
+    /// most providers, and so mdbx read transactions, open at once
+    max_providers: usize,
+    pool:          Mutex<Pool>,
+    checked_in:    Condvar
+}
+
+struct Pool {
+    idle:   Vec<StateProviderDatabase<StateProviderBox>>,
+    /// providers open, idle and checked out
+    opened: usize
+}
+
+impl StateProviders {
+    fn new(open: Option<Box<StateProviderOpener>>, max_providers: usize, pool: Pool) -> Self {
+        Self { open, max_providers, pool: Mutex::new(pool), checked_in: Condvar::new() }
+    }
+
+    fn check_in(&self, provider: StateProviderDatabase<StateProviderBox>) {
+        let mut pool = self.pool.lock().unwrap_or_else(PoisonError::into_inner);
+        if pool.idle.len() < self.max_providers {
+            pool.idle.push(provider);
+        } else {
+            pool.opened -= 1;
+        }
+        drop(pool);
+
+        self.checked_in.notify_one();
+    }
+
+    /// gives up a slot reserved for a provider that failed to open
+    fn release(&self) {
+        self.pool
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .opened -= 1;
+        self.checked_in.notify_one();
+    }
+}
+
+/// a state provider checked out of a [`RethLibmdbxDatabaseRef`]. it is checked
+/// back in when the guard is dropped, unwinding included
+pub struct StateProviderGuard {
+    providers: Arc<StateProviders>,
+    provider:  Option<StateProviderDatabase<StateProviderBox>>
+}
+
+impl Deref for StateProviderGuard {
+    type Target = StateProviderDatabase<StateProviderBox>;
+
+    fn deref(&self) -> &Self::Target {
+        self.provider.as_ref().expect("only taken on drop")
+    }
+}
+
+impl DerefMut for StateProviderGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.provider.as_mut().expect("only taken on drop")
+    }
+}
+
+impl Drop for StateProviderGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            self.providers.check_in(provider);
+        }
+    }
+}
 
 impl RethLibmdbxDatabaseRef {
+    /// a database over a single provider. a read while the provider is checked
+    /// out errors instead of waiting for it, use [`Self::with_opener`] for
+    /// concurrent readers
     pub fn new(this: StateProviderDatabase<StateProviderBox>) -> Self {
-        Self(Arc::new(Mutex::new(this)))
+        let pool = Pool { idle: vec![this], opened: 1 };
+        Self { providers: Arc::new(StateProviders::new(None, 1, pool)), pinned_block: None }
+    }
+
+    /// a database that opens a new provider with `open` whenever every
+    /// existing one is in use, up to `max_providers`. past that, reads wait
+    /// for a provider to be checked back in. `open` must always return state
+    /// for the same block
+    pub fn with_opener(
+        max_providers: usize,
+        open: impl Fn() -> eyre::Result<StateProviderBox> + Send + Sync + 'static
+    ) -> Self {
+        let pool = Pool { idle: Vec::new(), opened: 0 };
+        let providers = StateProviders::new(Some(Box::new(open)), max_providers.max(1), pool);
+        Self { providers: Arc::new(providers), pinned_block: None }
     }
 
-    pub fn state_provider(&self) -> StateProviderDatabase<StateProviderBox> {
-        let this = self.clone();
-        Arc::try_unwrap(this.0)
-            .expect("multiple references to RethLibmdbxDatabaseRef")
-            .into_inner()
-            .expect("mutex poisoned")
+    /// storage reads through [`uniswap_storage::StorageSlotFetcher`] error if
+    /// they request a block other than `block`
+    pub fn with_pinned_block(mut self, block: PinnedBlock) -> Self {
+        self.pinned_block = Some(block);
+        self
+    }
+
+    pub fn pinned_block(&self) -> Option<PinnedBlock> {
+        self.pinned_block
+    }
+
+    /// checks out an idle provider, or opens a new one below the cap. with an
+    /// opener it waits at the cap for a provider to be checked back in,
+    /// without one it errors if every provider is checked out
+    pub fn state_provider(&self) -> eyre::Result<StateProviderGuard> {
+        let providers = &self.providers;
+        let mut pool = providers.pool.lock().map_err(|e| eyre::eyre!("{e}"))?;
+
+        let provider = loop {
+            if let Some(provider) = pool.idle.pop() {
+                break provider;
+            }
+
+            let Some(open) = &providers.open else {
+                eyre::bail!("every state provider of this RethLibmdbxDatabaseRef is checked out")
+            };
+
+            if pool.opened < providers.max_providers {
+                pool.opened += 1;
+                drop(pool);
+
+                match open() {
+                    Ok(provider) => break StateProviderDatabase::new(provider),
+                    Err(e) => {
+                        providers.release();
+                        return Err(e);
+                    }
+                }
+            }
+
+            pool = providers
+                .checked_in
+                .wait(pool)
+                .map_err(|e| eyre::eyre!("{e}"))?;
+        };
+
+        Ok(StateProviderGuard { providers: providers.clone(), provider: Some(provider) })
+    }
+
+    /// runs `f` with a provider that no other reader is using
+    pub fn with_state_provider<R>(&self, f: impl FnOnce(&StateProviderDatabase<StateProviderBox>) -> R) -> eyre::Result<R> {
+        let provider = self.state_provider()?;
+        Ok(f(&provider))
     }
 }
 
@@ -30,35 +179,23 @@ impl DatabaseRef for RethLibmdbxDatabaseRef {
     type Error = RevmUtilError;
 
     fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
-        let db = self
-            .0
-            .lock()
-            .map_err(|e| RevmUtilError(eyre::eyre!("{e}")))?;
-        reth_revm::DatabaseRef::basic_ref(&*db, address).map_err(RevmUtilError::as_value)
+        self.with_state_provider(|db| reth_revm::DatabaseRef::basic_ref(db, address))?
+            .map_err(RevmUtilError::as_value)
     }
 
     fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
-        let db = self
-            .0
-            .lock()
-            .map_err(|e| RevmUtilError(eyre::eyre!("{e}")))?;
-        reth_revm::DatabaseRef::code_by_hash_ref(&*db, code_hash).map_err(RevmUtilError::as_value)
+        self.with_state_provider(|db| reth_revm::DatabaseRef::code_by_hash_ref(db, code_hash))?
+            .map_err(RevmUtilError::as_value)
     }
 
     fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
-        let db = self
-            .0
-            .lock()
-            .map_err(|e| RevmUtilError(eyre::eyre!("{e}")))?;
-        reth_revm::DatabaseRef::storage_ref(&*db, address, index).map_err(RevmUtilError::as_value)
+        self.with_state_provider(|db| reth_revm::DatabaseRef::storage_ref(db, address, index))?
+            .map_err(RevmUtilError::as_value)
     }
 
     fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
-        let db = self
-            .0
-            .lock()
-            .map_err(|e| RevmUtilError(eyre::eyre!("{e}")))?;
-        reth_revm::DatabaseRef::block_hash_ref(&*db, number).map_err(RevmUtilError::as_value)
+        self.with_state_provider(|db| reth_revm::DatabaseRef::block_hash_ref(db, number))?
+            .map_err(RevmUtilError::as_value)
     }
 }
 
@@ -110,9 +247,13 @@ mod _uniswap_storage {
     #[async_trait::async_trait]
     impl StorageSlotFetcher for RethLibmdbxDatabaseRef {
         async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
-            let _ = block_id;
-            let db = self.0.lock().map_err(|e| eyre::eyre!("{e}"))?;
-            Ok(db.storage(address, key)?.unwrap_or_default())
+            if let Some(pinned_block) = self.pinned_block() {
+                pinned_block.ensure(block_id)?;
+            }
+
+            Ok(self
+                .with_state_provider(|db| db.storage(address, key))??
+                .unwrap_or_default())
         }
     }
 
@@ -1,21 +1,41 @@
-use alloy_eips::BlockId;
-use alloy_primitives::ChainId;
+use alloy_consensus::BlockHeader;
+use alloy_eips::{BlockId, eip1559::BaseFeeParams, eip2930::{AccessList, AccessListResult}, eip7840::BlobParams};
+use alloy_primitives::{BlockNumber, Bytes, ChainId, U256};
+use alloy_rpc_types::{
+    TransactionRequest,
+    state::EvmOverrides,
+    trace::geth::{CallConfig, CallFrame, PreStateConfig, PreStateFrame}
+};
+use alloy_sol_types::SolCall;
+use reth_chainspec::EthereumHardforks;
 use revm::{
     Context, DatabaseRef, Journal, MainBuilder, MainContext,
     context::{BlockEnv, CfgEnv, Evm, TxEnv},
     handler::{EthFrame, EthPrecompiles, EvmTr, instructions::EthInstructions},
-    interpreter::interpreter::EthInterpreter
+    interpreter::interpreter::EthInterpreter,
+    primitives::hardfork::SpecId
 };
 use revm_database::CacheDB;
+use revm_inspectors::{
+    access_list::AccessListInspector,
+    tracing::{TracingInspector, TracingInspectorConfig}
+};
+
+use crate::traits::{
+    BlockProgression, BundleOutcome, BundleTransaction, LayeredMainnetRevm, SimulatedBlock, SimulatedBlockOutcome,
+    SimulationOutcome, StorageAccessInspector, apply_block_overrides, apply_state_overrides, call_lens_mainnet_evm,
+    call_trace_mainnet_evm, create_access_list_mainnet_evm, estimate_gas_mainnet_evm, prestate_trace_mainnet_evm,
+    simulate_mainnet_blocks, simulate_mainnet_bundle, simulate_mainnet_evm, storage_access_mainnet_evm
+};
+#[cfg(feature = "op-revm")]
+use crate::traits::{simulate_op_blocks, simulate_op_bundle, simulate_op_evm};
+
 type NetworkRevmContext<DB, TX, CFG, CHAIN> = Context<BlockEnv, TX, CFG, CacheDB<DB>, Journal<CacheDB<DB>>, CHAIN>;
 
-type MainnetRevmEvm<DB> = Evm<
-    NetworkRevmContext<DB, TxEnv, CfgEnv, ()>,
-    (),
-    EthInstructions<EthInterpreter, NetworkRevmContext<DB, TxEnv, CfgEnv, ()>>,
-    EthPrecompiles,
-    EthFrame
->;
+pub type MainnetRevmContext<DB> = NetworkRevmContext<DB, TxEnv, CfgEnv, ()>;
+
+pub type MainnetRevmEvm<DB, I = ()> =
+    Evm<MainnetRevmContext<DB>, I, EthInstructions<EthInterpreter, MainnetRevmContext<DB>>, EthPrecompiles, EthFrame>;
 
 pub fn empty_mainnet_revm<DB: DatabaseRef>(
     db: CacheDB<DB>,
@@ -35,24 +55,107 @@ pub fn empty_mainnet_revm<DB: DatabaseRef>(
     evm
 }
 
+/// the latest evm spec active at a block of `chain_spec`
+pub fn spec_id_at<C: EthereumHardforks>(chain_spec: &C, number: BlockNumber, timestamp: u64) -> SpecId {
+    if chain_spec.is_osaka_active_at_timestamp(timestamp) {
+        SpecId::OSAKA
+    } else if chain_spec.is_prague_active_at_timestamp(timestamp) {
+        SpecId::PRAGUE
+    } else if chain_spec.is_cancun_active_at_timestamp(timestamp) {
+        SpecId::CANCUN
+    } else if chain_spec.is_shanghai_active_at_timestamp(timestamp) {
+        SpecId::SHANGHAI
+    } else if chain_spec.is_paris_active_at_block(number) {
+        SpecId::MERGE
+    } else if chain_spec.is_london_active_at_block(number) {
+        SpecId::LONDON
+    } else if chain_spec.is_berlin_active_at_block(number) {
+        SpecId::BERLIN
+    } else if chain_spec.is_istanbul_active_at_block(number) {
+        SpecId::ISTANBUL
+    } else if chain_spec.is_petersburg_active_at_block(number) {
+        SpecId::PETERSBURG
+    } else if chain_spec.is_byzantium_active_at_block(number) {
+        SpecId::BYZANTIUM
+    } else if chain_spec.is_spurious_dragon_active_at_block(number) {
+        SpecId::SPURIOUS_DRAGON
+    } else if chain_spec.is_tangerine_whistle_active_at_block(number) {
+        SpecId::TANGERINE
+    } else if chain_spec.is_homestead_active_at_block(number) {
+        SpecId::HOMESTEAD
+    } else {
+        SpecId::FRONTIER
+    }
+}
+
+/// the [`BlockEnv`] of `header`. `blob_params` prices its excess blob gas
+pub fn block_env_from_header<H: BlockHeader>(header: &H, blob_params: Option<BlobParams>) -> BlockEnv {
+    let mut block_env = BlockEnv {
+        number: U256::from(header.number()),
+        beneficiary: header.beneficiary(),
+        timestamp: U256::from(header.timestamp()),
+        gas_limit: header.gas_limit(),
+        basefee: header.base_fee_per_gas().unwrap_or_default(),
+        difficulty: header.difficulty(),
+        prevrandao: header.mix_hash(),
+        ..Default::default()
+    };
+
+    if let (Some(excess_blob_gas), Some(blob_params)) = (header.excess_blob_gas(), blob_params) {
+        block_env.set_blob_excess_gas_and_price(excess_blob_gas, blob_params.update_fraction as u64);
+    }
+
+    block_env
+}
+
+/// the [`BlockEnv`] of the block built on top of `parent`, `block_time`
+/// seconds later. the coinbase and prevrandao are unknown until the block is
+/// built, so the parent's are kept
+pub fn next_block_env<H: BlockHeader>(
+    parent: &H,
+    block_time: u64,
+    base_fee_params: BaseFeeParams,
+    blob_params: Option<BlobParams>
+) -> BlockEnv {
+    let mut block_env = BlockEnv {
+        number: U256::from(parent.number() + 1),
+        beneficiary: parent.beneficiary(),
+        timestamp: U256::from(parent.timestamp() + block_time),
+        gas_limit: parent.gas_limit(),
+        basefee: parent
+            .next_block_base_fee(base_fee_params)
+            .unwrap_or_default(),
+        prevrandao: parent.mix_hash(),
+        ..Default::default()
+    };
+
+    if let Some(blob_params) = blob_params
+        && let Some(excess_blob_gas) = parent.next_block_excess_blob_gas(blob_params)
+    {
+        block_env.set_blob_excess_gas_and_price(excess_blob_gas, blob_params.update_fraction as u64);
+    }
+
+    block_env
+}
+
 #[cfg(feature = "op-revm")]
-pub use op_impl::empty_op_mainnet_revm;
+pub use op_impl::{OptimismRevmContext, OptimismRevmEvm, empty_op_mainnet_revm, known_op_spec_at, op_l1_block_info};
 #[cfg(feature = "op-revm")]
 pub use op_revm::OpTransaction;
 
 #[cfg(feature = "op-revm")]
 mod op_impl {
     use op_revm::{DefaultOp, L1BlockInfo, OpBuilder, OpEvm, OpSpecId, OpTransaction, precompiles::OpPrecompiles};
+    use reth_chainspec::EthChainSpec;
+    use reth_optimism_chainspec::{BASE_MAINNET, BASE_SEPOLIA, OP_MAINNET, OP_SEPOLIA};
     use revm::handler::EvmTr;
 
     use super::*;
 
-    type OptimismRevmEvm<DB> = OpEvm<
-        NetworkRevmContext<DB, OpTransaction<TxEnv>, CfgEnv<OpSpecId>, L1BlockInfo>,
-        (),
-        EthInstructions<EthInterpreter, NetworkRevmContext<DB, OpTransaction<TxEnv>, CfgEnv<OpSpecId>, L1BlockInfo>>,
-        OpPrecompiles
-    >;
+    pub type OptimismRevmContext<DB> = NetworkRevmContext<DB, OpTransaction<TxEnv>, CfgEnv<OpSpecId>, L1BlockInfo>;
+
+    pub type OptimismRevmEvm<DB, I = ()> =
+        OpEvm<OptimismRevmContext<DB>, I, EthInstructions<EthInterpreter, OptimismRevmContext<DB>>, OpPrecompiles>;
 
     pub fn empty_op_mainnet_revm<DB: DatabaseRef>(
         db: CacheDB<DB>,
@@ -71,6 +174,67 @@ mod op_impl {
 
         evm
     }
+
+    /// the l1 block info of `block_env`'s block, read from the `L1Block`
+    /// predeploy in `db`. which fields are read depends on `spec`: the
+    /// ecotone scalars and blob base fee, and the isthmus operator fee params
+    pub fn op_l1_block_info<DB: DatabaseRef>(
+        db: &mut CacheDB<DB>,
+        block_env: &BlockEnv,
+        spec: OpSpecId
+    ) -> eyre::Result<L1BlockInfo> {
+        L1BlockInfo::try_fetch(db, block_env.number, spec).map_err(|e| eyre::eyre!("{e:?}"))
+    }
+
+    /// the op evm spec active at `timestamp` on `chain_id`. `None` unless the
+    /// hardforks of the chain are known without a node
+    pub fn known_op_spec_at(chain_id: ChainId, timestamp: u64) -> Option<OpSpecId> {
+        [&OP_MAINNET, &OP_SEPOLIA, &BASE_MAINNET, &BASE_SEPOLIA]
+            .into_iter()
+            .find(|chain_spec| chain_spec.chain_id() == chain_id)
+            .map(|chain_spec| reth_optimism_evm::revm_spec_by_timestamp_after_bedrock(&***chain_spec, timestamp))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use alloy_primitives::U256;
+        use op_revm::constants::{
+            ECOTONE_L1_BLOB_BASE_FEE_SLOT, ECOTONE_L1_FEE_SCALARS_SLOT, L1_BASE_FEE_SLOT, L1_BLOCK_CONTRACT
+        };
+        use revm_database::EmptyDB;
+
+        use super::*;
+
+        #[test]
+        fn test_known_op_spec_at() {
+            // the ecotone activation on op mainnet
+            assert_eq!(known_op_spec_at(10, 1_710_374_400), Some(OpSpecId::CANYON));
+            assert_eq!(known_op_spec_at(10, 1_710_374_401), Some(OpSpecId::ECOTONE));
+            assert_eq!(known_op_spec_at(1, 1_710_374_401), None);
+        }
+
+        #[test]
+        fn test_op_l1_block_info() {
+            let mut db = CacheDB::new(EmptyDB::default());
+            // the base fee scalar is packed in bytes 16..20 and the blob base
+            // fee scalar in bytes 20..24
+            let scalars = U256::from(1_368u64) << 96 | U256::from(810_949u64) << 64;
+            for (slot, value) in [
+                (L1_BASE_FEE_SLOT, U256::from(7)),
+                (ECOTONE_L1_BLOB_BASE_FEE_SLOT, U256::from(3)),
+                (ECOTONE_L1_FEE_SCALARS_SLOT, scalars)
+            ] {
+                db.insert_account_storage(L1_BLOCK_CONTRACT, slot, value)
+                    .unwrap();
+            }
+
+            let info = op_l1_block_info(&mut db, &BlockEnv::default(), OpSpecId::ECOTONE).unwrap();
+            assert_eq!(info.l1_base_fee, U256::from(7));
+            assert_eq!(info.l1_base_fee_scalar, U256::from(1_368));
+            assert_eq!(info.l1_blob_base_fee, Some(U256::from(3)));
+            assert_eq!(info.l1_blob_base_fee_scalar, Some(U256::from(810_949)));
+        }
+    }
 }
 
 #[auto_impl::auto_impl(&, Box, Arc)]
@@ -85,6 +249,286 @@ pub trait EthRevm {
     fn make_cache_db(&self, params: &Self::Params) -> eyre::Result<CacheDB<Self::InnerDb>> {
         Ok(CacheDB::new(self.make_inner_db(params)?))
     }
+
+    /// `the block env of params.block_id()`. a `pending` block id is the block
+    /// after `latest`. without access to headers this is revm's default block
+    /// env
+    fn make_block_env(&self, _params: &Self::Params) -> eyre::Result<BlockEnv> {
+        Ok(BlockEnv::default())
+    }
+
+    /// `the cfg env of the block`. without a chain spec this is the latest
+    /// spec
+    fn make_cfg_env(&self, params: &Self::Params, _block_env: &BlockEnv) -> eyre::Result<CfgEnv> {
+        Ok(CfgEnv::default().with_chain_id(params.chain_id()))
+    }
+
+    /// `makes a new cache db and the block env of params.block_id()`, with
+    /// `overrides` applied to both
+    fn make_overridden_cache_db(
+        &self,
+        params: &Self::Params,
+        overrides: &EvmOverrides
+    ) -> eyre::Result<(CacheDB<Self::InnerDb>, BlockEnv)> {
+        let mut db = self.make_cache_db(params)?;
+        let mut block_env = self.make_block_env(params)?;
+
+        if let Some(state) = &overrides.state {
+            apply_state_overrides(&mut db, state)?;
+        }
+        if let Some(block) = &overrides.block {
+            apply_block_overrides(&mut block_env, &mut db, block);
+        }
+
+        Ok((db, block_env))
+    }
+
+    /// `makes a mainnet evm over a new cache db, configured for
+    /// params.block_id()`
+    fn make_mainnet_revm(
+        &self,
+        params: &Self::Params,
+        disable_nonce_check: bool
+    ) -> eyre::Result<MainnetRevmEvm<Self::InnerDb>> {
+        self.make_mainnet_revm_with_overrides(params, &EvmOverrides::default(), disable_nonce_check)
+    }
+
+    /// [`EthRevm::make_mainnet_revm`] with `overrides` applied. the inner
+    /// database is left untouched
+    fn make_mainnet_revm_with_overrides(
+        &self,
+        params: &Self::Params,
+        overrides: &EvmOverrides,
+        disable_nonce_check: bool
+    ) -> eyre::Result<MainnetRevmEvm<Self::InnerDb>> {
+        self.make_mainnet_revm_with_inspector(params, overrides, (), disable_nonce_check)
+    }
+
+    /// [`EthRevm::make_mainnet_revm_with_overrides`] with `inspector`. it is
+    /// only called by the `inspect_*` methods of the evm
+    fn make_mainnet_revm_with_inspector<I>(
+        &self,
+        params: &Self::Params,
+        overrides: &EvmOverrides,
+        inspector: I,
+        disable_nonce_check: bool
+    ) -> eyre::Result<MainnetRevmEvm<Self::InnerDb, I>> {
+        let (db, block_env) = self.make_overridden_cache_db(params, overrides)?;
+        let mut cfg_env = self.make_cfg_env(params, &block_env)?;
+        cfg_env.disable_nonce_check = disable_nonce_check;
+
+        Ok(Context::mainnet()
+            .with_block(block_env)
+            .with_cfg(cfg_env)
+            .with_db(db)
+            .build_mainnet_with_inspector(inspector))
+    }
+
+    /// [`EthRevm::make_mainnet_revm`] with checkpoints over its cache db
+    fn make_layered_mainnet_revm(&self, params: &Self::Params) -> eyre::Result<LayeredMainnetRevm<Self::InnerDb>> {
+        Ok(LayeredMainnetRevm::new(self.make_mainnet_revm(params, false)?))
+    }
+
+    /// `the op spec of the block`, told from its timestamp on the op stack
+    /// chains of [`known_op_spec_at`]. on other chains this is the latest op
+    /// spec
+    #[cfg(feature = "op-revm")]
+    fn make_op_spec(&self, params: &Self::Params, block_env: &BlockEnv) -> eyre::Result<op_revm::OpSpecId> {
+        Ok(known_op_spec_at(params.chain_id(), block_env.timestamp.saturating_to()).unwrap_or_default())
+    }
+
+    /// `makes an op evm over a new cache db, configured for
+    /// params.block_id()`, with the l1 block info of the block loaded
+    #[cfg(feature = "op-revm")]
+    fn make_op_revm(
+        &self,
+        params: &Self::Params,
+        disable_nonce_check: bool
+    ) -> eyre::Result<OptimismRevmEvm<Self::InnerDb>> {
+        self.make_op_revm_with_overrides(params, &EvmOverrides::default(), disable_nonce_check)
+    }
+
+    /// [`EthRevm::make_op_revm`] with `overrides` applied. the inner
+    /// database is left untouched
+    #[cfg(feature = "op-revm")]
+    fn make_op_revm_with_overrides(
+        &self,
+        params: &Self::Params,
+        overrides: &EvmOverrides,
+        disable_nonce_check: bool
+    ) -> eyre::Result<OptimismRevmEvm<Self::InnerDb>> {
+        self.make_op_revm_with_inspector(params, overrides, (), disable_nonce_check)
+    }
+
+    /// [`EthRevm::make_op_revm_with_overrides`] with `inspector`. it is only
+    /// called by the `inspect_*` methods of the evm
+    #[cfg(feature = "op-revm")]
+    fn make_op_revm_with_inspector<I>(
+        &self,
+        params: &Self::Params,
+        overrides: &EvmOverrides,
+        inspector: I,
+        disable_nonce_check: bool
+    ) -> eyre::Result<OptimismRevmEvm<Self::InnerDb, I>> {
+        use op_revm::{DefaultOp, OpBuilder};
+
+        let (mut db, block_env) = self.make_overridden_cache_db(params, overrides)?;
+        let spec = self.make_op_spec(params, &block_env)?;
+        let l1_block_info = op_l1_block_info(&mut db, &block_env, spec)?;
+
+        let mut cfg_env = CfgEnv::new_with_spec(spec).with_chain_id(params.chain_id());
+        cfg_env.disable_nonce_check = disable_nonce_check;
+
+        Ok(Context::op()
+            .with_block(block_env)
+            .with_cfg(cfg_env)
+            .with_chain(l1_block_info)
+            .with_db(db)
+            .build_op_with_inspector(inspector))
+    }
+
+    /// `simulates tx on top of params.block_id()`
+    fn simulate_tx(&self, params: &Self::Params, tx: TransactionRequest) -> eyre::Result<SimulationOutcome> {
+        self.simulate_tx_with_overrides(params, tx, &EvmOverrides::default())
+    }
+
+    /// `simulates tx on top of params.block_id()`, like `eth_call` with
+    /// state and block overrides
+    fn simulate_tx_with_overrides(
+        &self,
+        params: &Self::Params,
+        tx: TransactionRequest,
+        overrides: &EvmOverrides
+    ) -> eyre::Result<SimulationOutcome> {
+        simulate_mainnet_evm(&mut self.make_mainnet_revm_with_overrides(params, overrides, false)?, tx)
+    }
+
+    /// `simulates tx on top of params.block_id() with the op evm`, as a
+    /// deposit if `deposit` is set
+    #[cfg(feature = "op-revm")]
+    fn simulate_op_tx(
+        &self,
+        params: &Self::Params,
+        tx: TransactionRequest,
+        deposit: Option<op_revm::transaction::deposit::DepositTransactionParts>
+    ) -> eyre::Result<SimulationOutcome> {
+        simulate_op_evm(&mut self.make_op_revm(params, false)?, tx, deposit)
+    }
+
+    /// `simulates txs in order on top of params.block_id()`. see
+    /// [`simulate_mainnet_bundle`]
+    fn simulate_bundle(
+        &self,
+        params: &Self::Params,
+        txs: Vec<BundleTransaction>,
+        atomic: bool
+    ) -> eyre::Result<BundleOutcome> {
+        simulate_mainnet_bundle(&mut self.make_mainnet_revm(params, false)?, txs, atomic)
+    }
+
+    /// `simulates txs in order on top of params.block_id() with the op evm`
+    #[cfg(feature = "op-revm")]
+    fn simulate_op_bundle(
+        &self,
+        params: &Self::Params,
+        txs: Vec<BundleTransaction>,
+        atomic: bool
+    ) -> eyre::Result<BundleOutcome> {
+        simulate_op_bundle(&mut self.make_op_revm(params, false)?, txs, atomic)
+    }
+
+    /// `simulates blocks in order on top of params.block_id()`, advancing the
+    /// block env between them. see [`simulate_mainnet_blocks`]
+    fn simulate_blocks(
+        &self,
+        params: &Self::Params,
+        blocks: Vec<SimulatedBlock>,
+        progression: BlockProgression
+    ) -> eyre::Result<Vec<SimulatedBlockOutcome>> {
+        simulate_mainnet_blocks(&mut self.make_mainnet_revm(params, false)?, blocks, progression)
+    }
+
+    /// `simulates blocks in order on top of params.block_id() with the op
+    /// evm`
+    #[cfg(feature = "op-revm")]
+    fn simulate_op_blocks(
+        &self,
+        params: &Self::Params,
+        blocks: Vec<SimulatedBlock>,
+        progression: BlockProgression
+    ) -> eyre::Result<Vec<SimulatedBlockOutcome>> {
+        simulate_op_blocks(&mut self.make_op_revm(params, false)?, blocks, progression)
+    }
+
+    /// `deploys the lens creation_code on top of params.block_id() and calls
+    /// it`. see [`call_lens_mainnet_evm`]
+    fn call_lens<C: SolCall>(
+        &self,
+        params: &Self::Params,
+        creation_code: Bytes,
+        call: &C,
+        overrides: &EvmOverrides
+    ) -> eyre::Result<C::Return> {
+        call_lens_mainnet_evm(&mut self.make_mainnet_revm_with_overrides(params, overrides, false)?, creation_code, call)
+    }
+
+    /// `debug_traceCall` with the `callTracer`, run locally
+    fn trace_call(
+        &self,
+        params: &Self::Params,
+        tx: TransactionRequest,
+        overrides: &EvmOverrides,
+        config: CallConfig
+    ) -> eyre::Result<CallFrame> {
+        let inspector = TracingInspector::new(TracingInspectorConfig::from_geth_call_config(&config));
+        let evm = self.make_mainnet_revm_with_inspector(params, overrides, inspector, false)?;
+
+        call_trace_mainnet_evm(evm, tx, config)
+    }
+
+    /// `debug_traceCall` with the `prestateTracer`, run locally
+    fn trace_prestate(
+        &self,
+        params: &Self::Params,
+        tx: TransactionRequest,
+        overrides: &EvmOverrides,
+        config: PreStateConfig
+    ) -> eyre::Result<PreStateFrame> {
+        let inspector = TracingInspector::new(TracingInspectorConfig::from_geth_prestate_config(&config));
+        let evm = self.make_mainnet_revm_with_inspector(params, overrides, inspector, false)?;
+
+        prestate_trace_mainnet_evm(evm, tx, config)
+    }
+
+    /// `the storage slots tx reads or writes`, by account
+    fn trace_storage_access(
+        &self,
+        params: &Self::Params,
+        tx: TransactionRequest,
+        overrides: &EvmOverrides
+    ) -> eyre::Result<AccessList> {
+        let evm = self.make_mainnet_revm_with_inspector(params, overrides, StorageAccessInspector::default(), false)?;
+
+        storage_access_mainnet_evm(evm, tx)
+    }
+
+    /// `eth_createAccessList`, run locally
+    fn create_access_list(
+        &self,
+        params: &Self::Params,
+        tx: TransactionRequest,
+        overrides: &EvmOverrides
+    ) -> eyre::Result<AccessListResult> {
+        let inspector = AccessListInspector::new(AccessList::default());
+        let mut evm = self.make_mainnet_revm_with_inspector(params, overrides, inspector, false)?;
+
+        create_access_list_mainnet_evm(&mut evm, tx)
+    }
+
+    /// `eth_estimateGas`, run locally
+    fn estimate_gas(&self, params: &Self::Params, tx: TransactionRequest, overrides: &EvmOverrides) -> eyre::Result<u64> {
+        estimate_gas_mainnet_evm(&mut self.make_mainnet_revm_with_overrides(params, overrides, false)?, tx)
+    }
 }
 
 pub trait EthRevmInput: Send + Sync {
@@ -0,0 +1,417 @@
+use std::fmt::Debug;
+
+use alloy_primitives::{Address, B256, Bytes, Log, TxKind, U256};
+use alloy_rpc_types::TransactionRequest;
+use revm::{
+    DatabaseRef, ExecuteEvm,
+    context::{BlockEnv, CfgEnv, TxEnv},
+    context_interface::{
+        Cfg, ContextTr,
+        result::{EVMError, ExecutionResult, ResultAndState}
+    },
+    handler::EvmTr,
+    state::{AccountInfo, EvmState}
+};
+use revm_database::CacheDB;
+
+use crate::traits::MainnetRevmEvm;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationStatus {
+    Success,
+    /// `reason` is the decoded `Error(string)`, `Panic(uint256)` or custom
+    /// error, if it could be decoded
+    Revert { reason: Option<String> },
+    Halt { reason: String },
+    /// the transaction failed validation, e.g. on its nonce or gas limit, and
+    /// was not executed. only reported by bundles, a single simulation errors
+    Invalid { reason: String }
+}
+
+/// the error a simulation returns when the transaction fails validation. it
+/// is kept apart from database errors, so callers can tell the transaction
+/// apart from the state it ran against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionValidationError(pub String);
+
+impl std::fmt::Display for TransactionValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid transaction: {}", self.0)
+    }
+}
+
+impl std::error::Error for TransactionValidationError {}
+
+/// maps validation failures to [`TransactionValidationError`]
+pub(crate) fn execution_error<DBError: Debug, TxError: Debug>(e: EVMError<DBError, TxError>) -> eyre::Report {
+    match e {
+        EVMError::Transaction(e) => TransactionValidationError(format!("{e:?}")).into(),
+        e => eyre::eyre!("{e:?}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulatedAccountState {
+    pub nonce:     u64,
+    pub balance:   U256,
+    pub code_hash: B256
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulatedSlotDiff {
+    pub slot:   U256,
+    pub before: U256,
+    pub after:  U256
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedAccountDiff {
+    pub address: Address,
+    /// `None` if the account did not exist before the transaction
+    pub before:  Option<SimulatedAccountState>,
+    /// `None` if the transaction selfdestructed the account
+    pub after:   Option<SimulatedAccountState>,
+    pub storage: Vec<SimulatedSlotDiff>
+}
+
+/// the result of simulating one transaction. nothing is committed to the
+/// evm's database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationOutcome {
+    pub status:      SimulationStatus,
+    pub gas_used:    u64,
+    pub logs:        Vec<Log>,
+    /// the return data, or the revert data if the transaction reverted
+    pub output:      Bytes,
+    /// every account whose info or storage changed, sorted by address
+    pub state_diff:  Vec<SimulatedAccountDiff>,
+    /// the l1 data fee charged on top of the execution cost. only set for
+    /// non-deposit transactions on op chains
+    pub l1_data_fee: Option<U256>
+}
+
+impl SimulationOutcome {
+    pub fn is_success(&self) -> bool {
+        self.status == SimulationStatus::Success
+    }
+
+    /// the outcome of a transaction that failed validation
+    pub fn invalid(error: TransactionValidationError) -> Self {
+        Self {
+            status:      SimulationStatus::Invalid { reason: error.0 },
+            gas_used:    0,
+            logs:        Vec::new(),
+            output:      Bytes::new(),
+            state_diff:  Vec::new(),
+            l1_data_fee: None
+        }
+    }
+}
+
+/// simulates `tx` on `evm`. unset fields are filled from the evm's block and
+/// cfg env and the caller's nonce
+pub fn simulate_mainnet_evm<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    tx: TransactionRequest
+) -> eyre::Result<SimulationOutcome> {
+    Ok(execute_mainnet_evm(evm, tx)?.0)
+}
+
+/// [`simulate_mainnet_evm`], also returning the uncommitted state
+pub(crate) fn execute_mainnet_evm<DB: DatabaseRef>(
+    evm: &mut MainnetRevmEvm<DB>,
+    tx: TransactionRequest
+) -> eyre::Result<(SimulationOutcome, EvmState)> {
+    let tx = fill_request(tx, &evm.ctx_ref().block, &evm.ctx_ref().cfg, evm.ctx_ref().db_ref())?;
+
+    let disable_base_fee = disable_base_fee_for(&mut evm.ctx_mut().cfg, &tx);
+    let res = evm.transact(tx_env_from_request(&tx));
+    evm.ctx_mut().cfg.disable_base_fee = disable_base_fee;
+    let ResultAndState { result, state } = res.map_err(execution_error)?;
+
+    let outcome = simulation_outcome(result, &state, evm.ctx_ref().db_ref())?;
+
+    Ok((outcome, state))
+}
+
+/// fills the fields of `tx` that are needed to execute it. like `eth_call`,
+/// the gas defaults to the most a transaction may use in the block and a
+/// request without fees runs at a zero gas price
+pub(crate) fn fill_request<DB: DatabaseRef>(
+    mut tx: TransactionRequest,
+    block_env: &BlockEnv,
+    cfg: &impl Cfg,
+    db: &CacheDB<DB>
+) -> eyre::Result<TransactionRequest> {
+    let caller = tx.from.unwrap_or_default();
+    tx.from = Some(caller);
+    tx.chain_id = Some(tx.chain_id.unwrap_or(cfg.chain_id()));
+    tx.gas = Some(
+        tx.gas
+            .unwrap_or_else(|| block_env.gas_limit.min(cfg.tx_gas_limit_cap()))
+    );
+
+    if tx.nonce.is_none() {
+        let nonce = db
+            .basic_ref(caller)
+            .map_err(|e| eyre::eyre!("{e:?}"))?
+            .map(|info| info.nonce)
+            .unwrap_or_default();
+        tx.nonce = Some(nonce);
+    }
+
+    if tx.gas_price.is_none()
+        && tx.max_fee_per_gas.is_none()
+        && let Some(priority_fee) = tx.max_priority_fee_per_gas
+    {
+        tx.max_fee_per_gas = Some(block_env.basefee as u128 + priority_fee);
+    }
+
+    Ok(tx)
+}
+
+/// a filled `tx` without fees runs at a zero gas price, which only passes
+/// validation with the base fee check off. turns it off for such a `tx` and
+/// returns the flag to restore once `tx` ran
+pub(crate) fn disable_base_fee_for<SPEC>(cfg: &mut CfgEnv<SPEC>, tx: &TransactionRequest) -> bool {
+    let disable_base_fee = cfg.disable_base_fee;
+    if tx.gas_price.is_none() && tx.max_fee_per_gas.is_none() {
+        cfg.disable_base_fee = true;
+    }
+
+    disable_base_fee
+}
+
+/// `tx` must have been filled with [`fill_request`]
+pub(crate) fn tx_env_from_request(tx: &TransactionRequest) -> TxEnv {
+    let mut tx_env = TxEnv {
+        tx_type: tx.preferred_type() as u8,
+        caller: tx.from.unwrap_or_default(),
+        gas_limit: tx.gas.unwrap_or_default(),
+        gas_price: tx
+            .max_fee_per_gas
+            .or(tx.gas_price)
+            .unwrap_or_default(),
+        kind: tx.to.unwrap_or(TxKind::Create),
+        value: tx.value.unwrap_or_default(),
+        data: tx.input.input().cloned().unwrap_or_default(),
+        nonce: tx.nonce.unwrap_or_default(),
+        chain_id: tx.chain_id,
+        access_list: tx.access_list.clone().unwrap_or_default(),
+        gas_priority_fee: tx.max_priority_fee_per_gas,
+        blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
+        max_fee_per_blob_gas: tx.max_fee_per_blob_gas.unwrap_or_default(),
+        ..Default::default()
+    };
+
+    if let Some(authorization_list) = tx.authorization_list.clone() {
+        tx_env.set_signed_authorization(authorization_list);
+    }
+
+    tx_env
+}
+
+/// `db` must still hold the state from before the transaction
+pub(crate) fn simulation_outcome<H: Debug, DB: DatabaseRef>(
+    result: ExecutionResult<H>,
+    state: &EvmState,
+    db: &CacheDB<DB>
+) -> eyre::Result<SimulationOutcome> {
+    let gas_used = result.gas_used();
+    let (status, logs, output) = match result {
+        ExecutionResult::Success { logs, output, .. } => (SimulationStatus::Success, logs, output.into_data()),
+        ExecutionResult::Revert { output, .. } => {
+            (SimulationStatus::Revert { reason: alloy_sol_types::decode_revert_reason(&output) }, Vec::new(), output)
+        }
+        ExecutionResult::Halt { reason, .. } => {
+            (SimulationStatus::Halt { reason: format!("{reason:?}") }, Vec::new(), Bytes::new())
+        }
+    };
+
+    Ok(SimulationOutcome { status, gas_used, logs, output, state_diff: state_diff(state, db)?, l1_data_fee: None })
+}
+
+fn state_diff<DB: DatabaseRef>(state: &EvmState, db: &CacheDB<DB>) -> eyre::Result<Vec<SimulatedAccountDiff>> {
+    let mut diffs = Vec::new();
+
+    for (address, account) in state {
+        let before = db
+            .basic_ref(*address)
+            .map_err(|e| eyre::eyre!("{e:?}"))?
+            .map(|info| account_state(&info));
+        let after = (!account.is_selfdestructed() && (before.is_some() || !account.info.is_empty()))
+            .then(|| account_state(&account.info));
+
+        let mut storage = account
+            .storage
+            .iter()
+            .filter(|(_, slot)| slot.is_changed())
+            .map(|(slot, value)| SimulatedSlotDiff {
+                slot:   *slot,
+                before: value.original_value(),
+                after:  value.present_value()
+            })
+            .collect::<Vec<_>>();
+        storage.sort_by_key(|s| s.slot);
+
+        if before != after || !storage.is_empty() {
+            diffs.push(SimulatedAccountDiff { address: *address, before, after, storage });
+        }
+    }
+
+    diffs.sort_by_key(|d| d.address);
+
+    Ok(diffs)
+}
+
+fn account_state(info: &AccountInfo) -> SimulatedAccountState {
+    SimulatedAccountState { nonce: info.nonce, balance: info.balance, code_hash: info.code_hash }
+}
+
+#[cfg(feature = "op-revm")]
+pub(crate) use op_impl::execute_op_evm;
+#[cfg(feature = "op-revm")]
+pub use op_impl::simulate_op_evm;
+
+#[cfg(feature = "op-revm")]
+mod op_impl {
+    use alloy_eips::eip2718::Encodable2718;
+    use alloy_primitives::Signature;
+    use op_revm::{
+        OpTransaction,
+        transaction::deposit::{DEPOSIT_TRANSACTION_TYPE, DepositTransactionParts}
+    };
+
+    use super::*;
+    use crate::traits::OptimismRevmEvm;
+
+    /// simulates `tx` on `evm`, as a deposit if `deposit` is set. for other
+    /// transactions the l1 data fee is estimated from the transaction signed
+    /// with a placeholder signature
+    pub fn simulate_op_evm<DB: DatabaseRef>(
+        evm: &mut OptimismRevmEvm<DB>,
+        tx: TransactionRequest,
+        deposit: Option<DepositTransactionParts>
+    ) -> eyre::Result<SimulationOutcome> {
+        Ok(execute_op_evm(evm, tx, deposit, None)?.0)
+    }
+
+    /// [`simulate_op_evm`], also returning the uncommitted state.
+    /// `enveloped_tx` is the signed transaction, if known, which the l1 data
+    /// fee is charged for
+    pub(crate) fn execute_op_evm<DB: DatabaseRef>(
+        evm: &mut OptimismRevmEvm<DB>,
+        tx: TransactionRequest,
+        deposit: Option<DepositTransactionParts>,
+        enveloped_tx: Option<Bytes>
+    ) -> eyre::Result<(SimulationOutcome, EvmState)> {
+        let tx = fill_request(tx, &evm.ctx_ref().block, &evm.ctx_ref().cfg, evm.ctx_ref().db_ref())?;
+        let mut base = tx_env_from_request(&tx);
+
+        let op_tx = match deposit {
+            Some(deposit) => {
+                base.tx_type = DEPOSIT_TRANSACTION_TYPE;
+                OpTransaction { base, enveloped_tx: None, deposit }
+            }
+            None => {
+                let enveloped_tx = match enveloped_tx {
+                    Some(enveloped_tx) => enveloped_tx,
+                    None => {
+                        // a request without fees runs at a zero gas price, and
+                        // is encoded with it
+                        let mut typed_tx = tx.clone();
+                        if typed_tx.gas_price.is_none() && typed_tx.max_fee_per_gas.is_none() {
+                            typed_tx.max_fee_per_gas = Some(0);
+                            typed_tx.max_priority_fee_per_gas = Some(0);
+                        }
+
+                        typed_tx
+                            .build_typed_tx()
+                            .map_err(|_| eyre::eyre!("transaction request is missing fields to encode it"))?
+                            .into_envelope(Signature::new(U256::MAX, U256::MAX, false))
+                            .encoded_2718()
+                            .into()
+                    }
+                };
+                OpTransaction { base, enveloped_tx: Some(enveloped_tx), deposit: Default::default() }
+            }
+        };
+        let enveloped_tx = op_tx.enveloped_tx.clone();
+
+        let disable_base_fee = disable_base_fee_for(&mut evm.ctx_mut().cfg, &tx);
+        let res = evm.transact(op_tx);
+        evm.ctx_mut().cfg.disable_base_fee = disable_base_fee;
+        let ResultAndState { result, state } = res.map_err(execution_error)?;
+
+        let mut outcome = simulation_outcome(result, &state, evm.ctx_ref().db_ref())?;
+
+        // the l1 block info is loaded from the state during execution
+        if let Some(enveloped_tx) = enveloped_tx {
+            let spec = evm.ctx_ref().cfg.spec;
+            outcome.l1_data_fee = Some(
+                evm.ctx_mut()
+                    .chain
+                    .calculate_tx_l1_cost(&enveloped_tx, spec)
+            );
+        }
+
+        Ok((outcome, state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use revm::primitives::hardfork::SpecId;
+    use revm_database::EmptyDB;
+
+    use super::*;
+    use crate::traits::empty_mainnet_revm;
+
+    #[test]
+    fn test_simulate_defaults_on_osaka() {
+        let from = address!("0x0000000000000000000000000000000000000001");
+        let to = address!("0x0000000000000000000000000000000000000002");
+
+        let mut evm = empty_mainnet_revm(CacheDB::new(EmptyDB::default()), 1, false);
+        evm.ctx_mut().cfg.spec = SpecId::OSAKA;
+        evm.ctx_mut().block.gas_limit = 60_000_000;
+        evm.ctx_mut().block.basefee = 1_000_000_000;
+
+        let tx = TransactionRequest::default().from(from).to(to);
+        let filled = fill_request(tx.clone(), &evm.ctx_ref().block, &evm.ctx_ref().cfg, evm.ctx_ref().db_ref()).unwrap();
+        assert_eq!(filled.gas, Some(1 << 24));
+
+        // the caller holds nothing, so this only passes at a zero gas price
+        let outcome = simulate_mainnet_evm(&mut evm, tx).unwrap();
+        assert!(outcome.is_success());
+        assert_eq!(outcome.gas_used, 21_000);
+        assert!(!evm.ctx_ref().cfg.disable_base_fee);
+    }
+
+    #[cfg(all(feature = "reth-db", not(feature = "ci")))]
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_simulate_tx() {
+        use alloy_eips::BlockId;
+
+        use crate::{
+            test_utils::{WETH, mainnet_client, weth_deposit_tx},
+            traits::{EthRevm, EthRevmParams}
+        };
+
+        let client = mainnet_client();
+        let outcome = client
+            .simulate_tx(&EthRevmParams { block_id: BlockId::latest(), chain_id: 1 }, weth_deposit_tx())
+            .unwrap();
+        assert!(outcome.is_success());
+        assert!(outcome.gas_used > 21_000);
+        assert_eq!(outcome.logs.len(), 1);
+        assert!(
+            outcome
+                .state_diff
+                .iter()
+                .any(|d| d.address == WETH && !d.storage.is_empty())
+        );
+    }
+}
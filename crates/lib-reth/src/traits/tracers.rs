@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use alloy_eips::eip2930::{AccessList, AccessListItem};
+use alloy_primitives::{Address, B256};
+use alloy_rpc_types::{
+    TransactionRequest,
+    trace::geth::{CallConfig, CallFrame, PreStateConfig, PreStateFrame}
+};
+use revm::{
+    DatabaseRef, InspectEvm, Inspector,
+    bytecode::opcode,
+    context::Evm,
+    context_interface::{ContextTr, result::ResultAndState},
+    handler::EvmTr,
+    interpreter::{Interpreter, interpreter_types::Jumps}
+};
+use revm_inspectors::tracing::TracingInspector;
+
+use crate::traits::{MainnetRevmContext, MainnetRevmEvm, disable_base_fee_for, fill_request, tx_env_from_request};
+
+/// runs `tx` on `evm` through its inspector. nothing is committed to the
+/// evm's database
+pub fn inspect_mainnet_evm<DB, I>(evm: &mut MainnetRevmEvm<DB, I>, tx: TransactionRequest) -> eyre::Result<ResultAndState>
+where
+    DB: DatabaseRef,
+    I: Inspector<MainnetRevmContext<DB>>
+{
+    let tx = fill_request(tx, &evm.ctx_ref().block, &evm.ctx_ref().cfg, evm.ctx_ref().db_ref())?;
+
+    let disable_base_fee = disable_base_fee_for(&mut evm.ctx_mut().cfg, &tx);
+    let res = evm.inspect_tx(tx_env_from_request(&tx));
+    evm.ctx_mut().cfg.disable_base_fee = disable_base_fee;
+
+    res.map_err(|e| eyre::eyre!("{e:?}"))
+}
+
+/// the `callTracer` frame of `tx`, in the shape `debug_traceCall` returns.
+/// the inspector must have been made from `config`
+pub fn call_trace_mainnet_evm<DB: DatabaseRef>(
+    mut evm: MainnetRevmEvm<DB, TracingInspector>,
+    tx: TransactionRequest,
+    config: CallConfig
+) -> eyre::Result<CallFrame> {
+    let res = inspect_mainnet_evm(&mut evm, tx)?;
+    let Evm { inspector, .. } = evm;
+
+    Ok(inspector
+        .into_geth_builder()
+        .geth_call_traces(config, res.result.gas_used()))
+}
+
+/// the `prestateTracer` frame of `tx`, in the shape `debug_traceCall`
+/// returns. the inspector must have been made from `config`
+pub fn prestate_trace_mainnet_evm<DB: DatabaseRef>(
+    mut evm: MainnetRevmEvm<DB, TracingInspector>,
+    tx: TransactionRequest,
+    config: PreStateConfig
+) -> eyre::Result<PreStateFrame> {
+    let res = inspect_mainnet_evm(&mut evm, tx)?;
+    let Evm { ctx, inspector, .. } = evm;
+
+    inspector
+        .into_geth_builder()
+        .geth_prestate_traces(&res, &config, ctx.db_ref())
+        .map_err(|e| eyre::eyre!("{e:?}"))
+}
+
+/// every storage slot `tx` reads or writes, grouped by account. unlike an
+/// access list, the slots of the caller, the callee and precompiles are kept
+pub fn storage_access_mainnet_evm<DB: DatabaseRef>(
+    mut evm: MainnetRevmEvm<DB, StorageAccessInspector>,
+    tx: TransactionRequest
+) -> eyre::Result<AccessList> {
+    inspect_mainnet_evm(&mut evm, tx)?;
+    let Evm { inspector, .. } = evm;
+
+    Ok(inspector.into_access_list())
+}
+
+/// records the (address, slot) of every `SLOAD` and `SSTORE`, whichever
+/// account it runs in
+#[derive(Debug, Clone, Default)]
+pub struct StorageAccessInspector {
+    slots: BTreeMap<Address, BTreeSet<B256>>
+}
+
+impl StorageAccessInspector {
+    pub fn slots(&self) -> &BTreeMap<Address, BTreeSet<B256>> {
+        &self.slots
+    }
+
+    pub fn into_access_list(self) -> AccessList {
+        AccessList(
+            self.slots
+                .into_iter()
+                .map(|(address, slots)| AccessListItem { address, storage_keys: slots.into_iter().collect() })
+                .collect()
+        )
+    }
+}
+
+impl<CTX> Inspector<CTX> for StorageAccessInspector {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut CTX) {
+        if matches!(interp.bytecode.opcode(), opcode::SLOAD | opcode::SSTORE)
+            && let Ok(slot) = interp.stack.peek(0)
+        {
+            self.slots
+                .entry(interp.input.target_address)
+                .or_default()
+                .insert(B256::from(slot));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{U256, address};
+    use revm::{Context, MainBuilder, MainContext, bytecode::Bytecode, state::AccountInfo};
+    use revm_database::{CacheDB, EmptyDB};
+
+    use super::*;
+
+    #[test]
+    fn test_storage_access_includes_callee() {
+        let from = address!("0x0000000000000000000000000000000000000001");
+        let to = address!("0x0000000000000000000000000000000000000002");
+
+        // PUSH1 1 PUSH1 2 SSTORE PUSH1 3 SLOAD STOP
+        let code = Bytecode::new_raw(vec![0x60, 0x01, 0x60, 0x02, 0x55, 0x60, 0x03, 0x54, 0x00].into());
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(to, AccountInfo::from_bytecode(code));
+
+        let evm = Context::mainnet()
+            .with_db(db)
+            .build_mainnet_with_inspector(StorageAccessInspector::default());
+        let access_list = storage_access_mainnet_evm(evm, TransactionRequest::default().from(from).to(to)).unwrap();
+
+        let slots = [U256::from(2), U256::from(3)].map(B256::from).to_vec();
+        assert_eq!(access_list, AccessList(vec![AccessListItem { address: to, storage_keys: slots }]));
+    }
+
+    #[cfg(all(feature = "reth-db", not(feature = "ci")))]
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial_test::serial]
+    async fn test_local_trace_call() {
+        use alloy_eips::BlockId;
+        use alloy_primitives::{Address, keccak256};
+        use alloy_rpc_types::{
+            state::EvmOverrides,
+            trace::geth::{CallConfig, PreStateConfig, PreStateFrame}
+        };
+        use alloy_sol_types::SolValue;
+
+        use crate::{
+            test_utils::{WETH, mainnet_client, weth_deposit_tx},
+            traits::{EthRevm, EthRevmParams}
+        };
+
+        let client = mainnet_client();
+        let params = EthRevmParams { block_id: BlockId::latest(), chain_id: 1 };
+        let overrides = EvmOverrides::default();
+        let tx = weth_deposit_tx();
+
+        let config = CallConfig { only_top_call: Some(false), with_log: Some(true) };
+        let frame = client
+            .trace_call(&params, tx.clone(), &overrides, config)
+            .unwrap();
+        assert_eq!(frame.to, Some(WETH));
+        assert_eq!(frame.logs.len(), 1);
+
+        let config = PreStateConfig { diff_mode: Some(true), ..Default::default() };
+        let prestate = client
+            .trace_prestate(&params, tx.clone(), &overrides, config)
+            .unwrap();
+        assert!(matches!(prestate, PreStateFrame::Diff(diff) if diff.post.contains_key(&WETH)));
+
+        // the deposit writes balanceOf[from], mapping slot 3
+        let balance_slot = keccak256((Address::ZERO, U256::from(3)).abi_encode());
+        let access_list = client
+            .trace_storage_access(&params, tx, &overrides)
+            .unwrap();
+        assert!(
+            access_list
+                .iter()
+                .any(|item| item.address == WETH && item.storage_keys.contains(&balance_slot))
+        );
+    }
+}
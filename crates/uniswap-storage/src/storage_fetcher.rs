@@ -1,7 +1,11 @@
-use alloy_eips::BlockId;
-use alloy_network::Network;
+use alloy_consensus::BlockHeader;
+use alloy_eips::{BlockId, BlockNumHash};
+use alloy_network::{BlockResponse, Network, primitives::HeaderResponse};
 use alloy_primitives::{Address, StorageKey, StorageValue};
-use alloy_provider::{Provider, RootProvider};
+use alloy_provider::{
+    Provider, RootProvider,
+    fillers::{FillProvider, TxFiller}
+};
 use auto_impl::auto_impl;
 
 #[auto_impl(&, Box, Arc)]
@@ -18,19 +22,80 @@ pub trait StorageSlotFetcher: Sync {
 #[async_trait::async_trait]
 impl<N: Network> StorageSlotFetcher for RootProvider<N> {
     async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
-        Ok(self
-            .get_storage_at(address, key.into())
-            .block_id(block_id)
-            .await?)
+        provider_storage_at(self, address, key, block_id).await
     }
 }
 
+/// any provider built by `ProviderBuilder`, whatever its fillers
+#[async_trait::async_trait]
+impl<F, P, N> StorageSlotFetcher for FillProvider<F, P, N>
+where
+    F: TxFiller<N>,
+    P: Provider<N>,
+    N: Network
+{
+    async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
+        provider_storage_at(self, address, key, block_id).await
+    }
+}
+
+async fn provider_storage_at<N: Network, P: Provider<N>>(
+    provider: &P,
+    address: Address,
+    key: StorageKey,
+    block_id: BlockId
+) -> eyre::Result<StorageValue> {
+    Ok(provider
+        .get_storage_at(address, key.into())
+        .block_id(block_id)
+        .await?)
+}
+
+/// resolves block ids, so reads made by id can be pinned to the block they
+/// were made at
+#[async_trait::async_trait]
+#[auto_impl(&, Box, Arc)]
+pub trait BlockNumHashFetcher: Sync {
+    async fn block_num_hash(&self, block_id: BlockId) -> eyre::Result<BlockNumHash>;
+}
+
+#[async_trait::async_trait]
+impl<N: Network> BlockNumHashFetcher for RootProvider<N> {
+    async fn block_num_hash(&self, block_id: BlockId) -> eyre::Result<BlockNumHash> {
+        provider_block_num_hash(self, block_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, P, N> BlockNumHashFetcher for FillProvider<F, P, N>
+where
+    F: TxFiller<N>,
+    P: Provider<N>,
+    N: Network
+{
+    async fn block_num_hash(&self, block_id: BlockId) -> eyre::Result<BlockNumHash> {
+        provider_block_num_hash(self, block_id).await
+    }
+}
+
+async fn provider_block_num_hash<N: Network, P: Provider<N>>(provider: &P, block_id: BlockId) -> eyre::Result<BlockNumHash> {
+    let block = provider
+        .get_block(block_id)
+        .await?
+        .ok_or_else(|| eyre::eyre!("block {block_id} not found"))?;
+
+    Ok(BlockNumHash::new(block.header().number(), block.header().hash()))
+}
+
 #[cfg(feature = "revm")]
 mod revm_impls {
-    use revm_database::{AlloyDB, CacheDB, DatabaseRef, WrapDatabaseAsync, async_db::DatabaseAsyncRef};
+    use revm_database::{AccountState, AlloyDB, CacheDB, DatabaseRef, WrapDatabaseAsync, async_db::DatabaseAsyncRef};
 
     use super::*;
 
+    /// reads at the block the db was built at. `AlloyDB` doesn't expose that
+    /// block, so `block_id` can't be checked here. lib-reth's `RpcDatabaseRef`
+    /// wraps it and errors on a read at any other block
     #[async_trait::async_trait]
     impl<P: Provider<N>, N: Network> StorageSlotFetcher for AlloyDB<N, P> {
         async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
@@ -39,6 +104,7 @@ mod revm_impls {
         }
     }
 
+    /// see the `AlloyDB` impl, `block_id` isn't checked either
     #[async_trait::async_trait]
     impl<S: StorageSlotFetcher + DatabaseAsyncRef> StorageSlotFetcher for WrapDatabaseAsync<S> {
         async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
@@ -48,12 +114,25 @@ mod revm_impls {
         }
     }
 
+    /// slots already in the cache, `None` if the read has to go to the inner db
+    fn cached_storage<S>(db: &CacheDB<S>, address: Address, key: StorageKey) -> Option<StorageValue> {
+        let account = db.cache.accounts.get(&address)?;
+        if let Some(value) = account.storage.get(&key.into()) {
+            return Some(*value);
+        }
+
+        matches!(account.account_state, AccountState::StorageCleared | AccountState::NotExisting)
+            .then_some(StorageValue::ZERO)
+    }
+
+    /// cache misses go through the inner fetcher so it can check `block_id`
     #[async_trait::async_trait]
     impl<S: StorageSlotFetcher + DatabaseRef> StorageSlotFetcher for CacheDB<S> {
         async fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
-            let _ = block_id;
-            self.storage_ref(address, key.into())
-                .map_err(|e| eyre::eyre!("{e:?}"))
+            match cached_storage(self, address, key) {
+                Some(value) => Ok(value),
+                None => StorageSlotFetcher::storage_at(&self.db, address, key, block_id).await
+            }
         }
     }
 
@@ -67,17 +146,17 @@ mod revm_impls {
 
     impl<S: StorageSlotFetcherSync + DatabaseRef> StorageSlotFetcherSync for CacheDB<S> {
         fn storage_at(&self, address: Address, key: StorageKey, block_id: BlockId) -> eyre::Result<StorageValue> {
-            let _ = block_id;
-            self.storage_ref(address, key.into())
-                .map_err(|e| eyre::eyre!("{e:?}"))
+            match cached_storage(self, address, key) {
+                Some(value) => Ok(value),
+                None => StorageSlotFetcherSync::storage_at(&self.db, address, key, block_id)
+            }
         }
     }
 }
 
 #[cfg(feature = "local-reth")]
 mod reth_db_impls {
-
-    use reth_provider::StateProviderFactory;
+    use reth_provider::{BlockReaderIdExt, StateProviderFactory};
     use reth_rpc::{EthApi, eth::RpcNodeCore};
     use reth_rpc_convert::RpcConvert;
 
@@ -108,6 +187,17 @@ mod reth_db_impls {
         }
     }
 
+    #[async_trait::async_trait]
+    impl<N, Rpc> BlockNumHashFetcher for EthApi<N, Rpc>
+    where
+        N: RpcNodeCore,
+        Rpc: RpcConvert
+    {
+        async fn block_num_hash(&self, block_id: BlockId) -> eyre::Result<BlockNumHash> {
+            local_block_num_hash(self.provider(), block_id)
+        }
+    }
+
     #[cfg(feature = "l2-angstrom")]
     #[async_trait::async_trait]
     impl<N, Rpc> StorageSlotFetcher for reth_optimism_rpc::OpEthApi<N, Rpc>
@@ -121,4 +211,60 @@ mod reth_db_impls {
             Ok(state_provider.storage(address, key)?.unwrap_or_default())
         }
     }
+    #[cfg(feature = "l2-angstrom")]
+    #[async_trait::async_trait]
+    impl<N, Rpc> BlockNumHashFetcher for reth_optimism_rpc::OpEthApi<N, Rpc>
+    where
+        N: RpcNodeCore,
+        Rpc: RpcConvert
+    {
+        async fn block_num_hash(&self, block_id: BlockId) -> eyre::Result<BlockNumHash> {
+            local_block_num_hash(self.eth_api().provider(), block_id)
+        }
+    }
+
+    fn local_block_num_hash<P: BlockReaderIdExt>(provider: &P, block_id: BlockId) -> eyre::Result<BlockNumHash> {
+        let header = provider
+            .sealed_header_by_id(block_id)?
+            .ok_or_else(|| eyre::eyre!("block {block_id} not found"))?;
+
+        Ok(header.num_hash())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use alloy_provider::ProviderBuilder;
+
+    use super::*;
+    use crate::test_utils::{USDC, eth_provider};
+
+    #[tokio::test]
+    async fn test_fill_provider_fetchers() {
+        let root = eth_provider().await;
+        let filled = ProviderBuilder::new().connect_provider(root.clone());
+        let block_number = 23998000;
+
+        let block = BlockNumHashFetcher::block_num_hash(&root, BlockId::number(block_number))
+            .await
+            .unwrap();
+        assert_eq!(block.number, block_number);
+        assert_eq!(
+            BlockNumHashFetcher::block_num_hash(&filled, BlockId::hash(block.hash))
+                .await
+                .unwrap(),
+            block
+        );
+
+        let slot = B256::with_last_byte(1);
+        assert_eq!(
+            StorageSlotFetcher::storage_at(&filled, USDC, slot, BlockId::hash(block.hash))
+                .await
+                .unwrap(),
+            StorageSlotFetcher::storage_at(&root, USDC, slot, BlockId::number(block_number))
+                .await
+                .unwrap()
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use alloy_primitives::{U256, aliases::I24};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct TickData {
     pub tick:                     I24,
     pub is_initialized:           bool,
@@ -0,0 +1,77 @@
+use alloy_primitives::aliases::U24;
+
+/// fees are in hundredths of a bip
+pub const PIPS_DENOMINATOR: u32 = 1_000_000;
+
+pub const MAX_LP_FEE: u32 = 1_000_000;
+/// the pool key fee of pools whose lp fee is set by their hook
+pub const DYNAMIC_FEE_FLAG: u32 = 0x800000;
+/// set on the fee a `beforeSwap` hook returns to override the lp fee of the
+/// swap
+pub const OVERRIDE_FEE_FLAG: u32 = 0x400000;
+
+/// the most protocol fee for a single direction
+pub const MAX_PROTOCOL_FEE: u32 = 1000;
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/LPFeeLibrary.sol
+///
+/// function isDynamicFee(uint24 self) internal pure returns (bool)
+pub fn is_dynamic_fee(fee: U24) -> bool {
+    fee.to::<u32>() == DYNAMIC_FEE_FLAG
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/LPFeeLibrary.sol
+///
+/// function validate(uint24 self) internal pure
+pub fn validate_lp_fee(fee: U24) -> eyre::Result<()> {
+    if fee.to::<u32>() > MAX_LP_FEE {
+        eyre::bail!("LPFeeTooLarge({fee})");
+    }
+
+    Ok(())
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/ProtocolFeeLibrary.sol
+///
+/// function getZeroForOneFee(uint24 self) internal pure returns (uint16)
+pub fn get_zero_for_one_fee(protocol_fee: U24) -> u16 {
+    (protocol_fee.to::<u32>() & 0xfff) as u16
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/ProtocolFeeLibrary.sol
+///
+/// function getOneForZeroFee(uint24 self) internal pure returns (uint16)
+pub fn get_one_for_zero_fee(protocol_fee: U24) -> u16 {
+    (protocol_fee.to::<u32>() >> 12) as u16
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/ProtocolFeeLibrary.sol
+///
+/// function calculateSwapFee(uint16 self, uint24 lpFee) internal pure returns
+/// (uint24 swapFee)
+///
+/// the protocol fee is taken first and the lp fee from what is left:
+/// `protocol_fee + lp_fee - protocol_fee * lp_fee / 1_000_000`
+pub fn calculate_swap_fee(protocol_fee: u16, lp_fee: U24) -> U24 {
+    let protocol_fee = (protocol_fee & 0xfff) as u64;
+    let lp_fee = lp_fee.to::<u64>();
+
+    U24::from(protocol_fee + lp_fee - protocol_fee * lp_fee / PIPS_DENOMINATOR as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_fee() {
+        let protocol_fee = U24::from((300 << 12) | 1000);
+        assert_eq!(get_zero_for_one_fee(protocol_fee), 1000);
+        assert_eq!(get_one_for_zero_fee(protocol_fee), 300);
+
+        assert_eq!(calculate_swap_fee(0, U24::from(3000)), U24::from(3000));
+        assert_eq!(calculate_swap_fee(1000, U24::ZERO), U24::from(1000));
+        assert_eq!(calculate_swap_fee(1000, U24::from(3000)), U24::from(3997));
+        assert_eq!(calculate_swap_fee(1000, U24::from(MAX_LP_FEE)), U24::from(MAX_LP_FEE));
+    }
+}
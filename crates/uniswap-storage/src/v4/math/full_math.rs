@@ -0,0 +1,86 @@
+use alloy_primitives::{U256, U512};
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/FullMath.sol
+///
+/// function mulDiv(uint256 a, uint256 b, uint256 denominator) internal pure
+/// returns (uint256 result)
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> eyre::Result<U256> {
+    if denominator.is_zero() {
+        eyre::bail!("mulDiv: the denominator is zero");
+    }
+
+    let result = U512::from(a) * U512::from(b) / U512::from(denominator);
+    if result > U512::from(U256::MAX) {
+        eyre::bail!("mulDiv: the result overflows uint256");
+    }
+
+    Ok(U256::from(result))
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/FullMath.sol
+///
+/// function mulDivRoundingUp(uint256 a, uint256 b, uint256 denominator)
+/// internal pure returns (uint256 result)
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> eyre::Result<U256> {
+    let result = mul_div(a, b, denominator)?;
+    if a.mul_mod(b, denominator).is_zero() {
+        return Ok(result);
+    }
+
+    result
+        .checked_add(U256::ONE)
+        .ok_or_else(|| eyre::eyre!("mulDivRoundingUp: the result overflows uint256"))
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/UnsafeMath.sol
+///
+/// function divRoundingUp(uint256 x, uint256 y) internal pure returns (uint256
+/// z)
+///
+/// like the evm, dividing by zero returns zero
+pub fn div_rounding_up(x: U256, y: U256) -> U256 {
+    if y.is_zero() {
+        return U256::ZERO;
+    }
+
+    let quotient = x / y;
+    if (x % y).is_zero() { quotient } else { quotient + U256::ONE }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/UnsafeMath.sol
+///
+/// function simpleMulDiv(uint256 a, uint256 b, uint256 denominator) internal
+/// pure returns (uint256 result)
+///
+/// the product wraps and dividing by zero returns zero
+pub fn simple_mul_div(a: U256, b: U256, denominator: U256) -> U256 {
+    if denominator.is_zero() {
+        return U256::ZERO;
+    }
+
+    a.wrapping_mul(b) / denominator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_div() {
+        let q128 = U256::ONE << 128;
+
+        let half = U256::from(50) * q128 / U256::from(100);
+        let one_and_a_half = U256::from(150) * q128 / U256::from(100);
+        assert_eq!(mul_div(q128, half, one_and_a_half).unwrap(), q128 / U256::from(3));
+        assert_eq!(mul_div(U256::MAX, U256::MAX, U256::MAX).unwrap(), U256::MAX);
+        assert!(mul_div(q128, q128, U256::ZERO).is_err());
+        assert!(mul_div(q128, q128, U256::ONE).is_err());
+
+        assert_eq!(mul_div_rounding_up(U256::from(7), U256::from(3), U256::from(2)).unwrap(), U256::from(11));
+        assert_eq!(mul_div_rounding_up(U256::from(6), U256::from(3), U256::from(2)).unwrap(), U256::from(9));
+        assert!(mul_div_rounding_up(U256::MAX, U256::MAX, U256::MAX - U256::ONE).is_err());
+
+        assert_eq!(div_rounding_up(U256::from(7), U256::from(2)), U256::from(4));
+        assert_eq!(div_rounding_up(U256::from(7), U256::ZERO), U256::ZERO);
+    }
+}
@@ -0,0 +1,21 @@
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/LiquidityMath.sol
+///
+/// function addDelta(uint128 x, int128 y) internal pure returns (uint128 z)
+pub fn add_delta(x: u128, y: i128) -> eyre::Result<u128> {
+    x.checked_add_signed(y)
+        .ok_or_else(|| eyre::eyre!("SafeCastOverflow: {x} + {y} is not a uint128"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_delta() {
+        assert_eq!(add_delta(1, 0).unwrap(), 1);
+        assert_eq!(add_delta(1, -1).unwrap(), 0);
+        assert_eq!(add_delta(1, 1).unwrap(), 2);
+        assert!(add_delta(0, -1).is_err());
+        assert!(add_delta(u128::MAX, 1).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+//! ports of the v4-core math libraries. every function rounds, truncates and
+//! fails where the solidity one does, so results match the chain bit for bit.
+//! reverts are returned as errors named after the solidity error
+pub mod fees;
+pub mod full_math;
+pub mod liquidity_math;
+pub mod sqrt_price_math;
+pub mod swap_math;
+pub mod tick_math;
@@ -0,0 +1,305 @@
+use alloy_primitives::{I256, U160, U256};
+
+use crate::v4::math::full_math::{div_rounding_up, mul_div, mul_div_rounding_up};
+
+const RESOLUTION: usize = 96;
+const Q96: U256 = U256::from_limbs([0, 1 << 32, 0, 0]);
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getNextSqrtPriceFromAmount0RoundingUp(uint160 sqrtPX96, uint128
+/// liquidity, uint256 amount, bool add) internal pure returns (uint160)
+pub fn get_next_sqrt_price_from_amount0_rounding_up(
+    sqrt_px96: U160,
+    liquidity: u128,
+    amount: U256,
+    add: bool
+) -> eyre::Result<U160> {
+    // we short circuit amount == 0 because the result is otherwise not
+    // guaranteed to equal the input price
+    if amount.is_zero() {
+        return Ok(sqrt_px96);
+    }
+
+    let sqrt_px96 = U256::from(sqrt_px96);
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+    // `product / amount == sqrtPX96` exactly when the product did not wrap
+    let (product, product_overflowed) = amount.overflowing_mul(sqrt_px96);
+
+    if add {
+        if !product_overflowed {
+            let (denominator, denominator_overflowed) = numerator1.overflowing_add(product);
+            if !denominator_overflowed {
+                // always fits 160 bits
+                return Ok(truncate_to_u160(mul_div_rounding_up(numerator1, sqrt_px96, denominator)?));
+            }
+        }
+
+        if sqrt_px96.is_zero() {
+            eyre::bail!("division by zero");
+        }
+        let denominator = (numerator1 / sqrt_px96)
+            .checked_add(amount)
+            .ok_or_else(|| eyre::eyre!("arithmetic overflow"))?;
+
+        Ok(truncate_to_u160(div_rounding_up(numerator1, denominator)))
+    } else {
+        // if the product overflows, we know the denominator underflows. in
+        // addition, we must check that the denominator does not underflow
+        if product_overflowed || numerator1 <= product {
+            eyre::bail!("PriceOverflow");
+        }
+
+        to_u160(mul_div_rounding_up(numerator1, sqrt_px96, numerator1 - product)?)
+    }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getNextSqrtPriceFromAmount1RoundingDown(uint160 sqrtPX96, uint128
+/// liquidity, uint256 amount, bool add) internal pure returns (uint160)
+pub fn get_next_sqrt_price_from_amount1_rounding_down(
+    sqrt_px96: U160,
+    liquidity: u128,
+    amount: U256,
+    add: bool
+) -> eyre::Result<U160> {
+    let sqrt_px96 = U256::from(sqrt_px96);
+    let liquidity = U256::from(liquidity);
+    let fits_u160 = amount <= U256::from(U160::MAX);
+
+    if add {
+        let quotient = if fits_u160 {
+            if liquidity.is_zero() {
+                eyre::bail!("division by zero");
+            }
+            (amount << RESOLUTION) / liquidity
+        } else {
+            mul_div(amount, Q96, liquidity)?
+        };
+
+        to_u160(
+            sqrt_px96
+                .checked_add(quotient)
+                .ok_or_else(|| eyre::eyre!("arithmetic overflow"))?
+        )
+    } else {
+        let quotient = if fits_u160 {
+            div_rounding_up(amount << RESOLUTION, liquidity)
+        } else {
+            mul_div_rounding_up(amount, Q96, liquidity)?
+        };
+
+        if sqrt_px96 <= quotient {
+            eyre::bail!("NotEnoughLiquidity");
+        }
+
+        Ok(truncate_to_u160(sqrt_px96 - quotient))
+    }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getNextSqrtPriceFromInput(uint160 sqrtPX96, uint128 liquidity,
+/// uint256 amountIn, bool zeroForOne) internal pure returns (uint160)
+pub fn get_next_sqrt_price_from_input(
+    sqrt_px96: U160,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool
+) -> eyre::Result<U160> {
+    if sqrt_px96.is_zero() || liquidity == 0 {
+        eyre::bail!("InvalidPriceOrLiquidity");
+    }
+
+    // round to make sure that we don't pass the target price
+    if zero_for_one {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_px96, liquidity, amount_in, true)
+    } else {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_px96, liquidity, amount_in, true)
+    }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getNextSqrtPriceFromOutput(uint160 sqrtPX96, uint128 liquidity,
+/// uint256 amountOut, bool zeroForOne) internal pure returns (uint160)
+pub fn get_next_sqrt_price_from_output(
+    sqrt_px96: U160,
+    liquidity: u128,
+    amount_out: U256,
+    zero_for_one: bool
+) -> eyre::Result<U160> {
+    if sqrt_px96.is_zero() || liquidity == 0 {
+        eyre::bail!("InvalidPriceOrLiquidity");
+    }
+
+    // round to make sure that we pass the target price
+    if zero_for_one {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_px96, liquidity, amount_out, false)
+    } else {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_px96, liquidity, amount_out, false)
+    }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getAmount0Delta(uint160 sqrtPriceAX96, uint160 sqrtPriceBX96,
+/// uint128 liquidity, bool roundUp) internal pure returns (uint256)
+pub fn get_amount0_delta(
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    liquidity: u128,
+    round_up: bool
+) -> eyre::Result<U256> {
+    let (sqrt_price_a_x96, sqrt_price_b_x96) = if sqrt_price_a_x96 > sqrt_price_b_x96 {
+        (U256::from(sqrt_price_b_x96), U256::from(sqrt_price_a_x96))
+    } else {
+        (U256::from(sqrt_price_a_x96), U256::from(sqrt_price_b_x96))
+    };
+
+    if sqrt_price_a_x96.is_zero() {
+        eyre::bail!("InvalidPrice");
+    }
+
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+    let numerator2 = sqrt_price_b_x96 - sqrt_price_a_x96;
+
+    if round_up {
+        Ok(div_rounding_up(mul_div_rounding_up(numerator1, numerator2, sqrt_price_b_x96)?, sqrt_price_a_x96))
+    } else {
+        Ok(mul_div(numerator1, numerator2, sqrt_price_b_x96)? / sqrt_price_a_x96)
+    }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getAmount1Delta(uint160 sqrtPriceAX96, uint160 sqrtPriceBX96,
+/// uint128 liquidity, bool roundUp) internal pure returns (uint256 amount1)
+pub fn get_amount1_delta(
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    liquidity: u128,
+    round_up: bool
+) -> eyre::Result<U256> {
+    let numerator = U256::from(if sqrt_price_a_x96 > sqrt_price_b_x96 {
+        sqrt_price_a_x96 - sqrt_price_b_x96
+    } else {
+        sqrt_price_b_x96 - sqrt_price_a_x96
+    });
+    let liquidity = U256::from(liquidity);
+
+    let amount1 = mul_div(liquidity, numerator, Q96)?;
+    if round_up && !liquidity.mul_mod(numerator, Q96).is_zero() { Ok(amount1 + U256::ONE) } else { Ok(amount1) }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getAmount0Delta(uint160 sqrtPriceAX96, uint160 sqrtPriceBX96,
+/// int128 liquidity) internal pure returns (int256)
+///
+/// the balance change of the caller: negative, owed to the pool, when
+/// `liquidity` is added
+pub fn get_amount0_delta_signed(sqrt_price_a_x96: U160, sqrt_price_b_x96: U160, liquidity: i128) -> eyre::Result<I256> {
+    if liquidity < 0 {
+        to_int256(get_amount0_delta(sqrt_price_a_x96, sqrt_price_b_x96, liquidity.unsigned_abs(), false)?)
+    } else {
+        Ok(-to_int256(get_amount0_delta(sqrt_price_a_x96, sqrt_price_b_x96, liquidity as u128, true)?)?)
+    }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SqrtPriceMath.sol
+///
+/// function getAmount1Delta(uint160 sqrtPriceAX96, uint160 sqrtPriceBX96,
+/// int128 liquidity) internal pure returns (int256)
+///
+/// the balance change of the caller: negative, owed to the pool, when
+/// `liquidity` is added
+pub fn get_amount1_delta_signed(sqrt_price_a_x96: U160, sqrt_price_b_x96: U160, liquidity: i128) -> eyre::Result<I256> {
+    if liquidity < 0 {
+        to_int256(get_amount1_delta(sqrt_price_a_x96, sqrt_price_b_x96, liquidity.unsigned_abs(), false)?)
+    } else {
+        Ok(-to_int256(get_amount1_delta(sqrt_price_a_x96, sqrt_price_b_x96, liquidity as u128, true)?)?)
+    }
+}
+
+/// `SafeCast.toInt256`
+pub(crate) fn to_int256(value: U256) -> eyre::Result<I256> {
+    I256::try_from(value).map_err(|_| eyre::eyre!("SafeCastOverflow: {value} is not an int256"))
+}
+
+/// `SafeCast.toUint160`
+fn to_u160(value: U256) -> eyre::Result<U160> {
+    if value > U256::from(U160::MAX) {
+        eyre::bail!("SafeCastOverflow: {value} is not a uint160");
+    }
+
+    Ok(truncate_to_u160(value))
+}
+
+/// `uint160(value)`
+fn truncate_to_u160(value: U256) -> U160 {
+    U160::from(value & U256::from(U160::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_ETHER: u128 = 1_000_000_000_000_000_000;
+
+    /// sqrt(1) and sqrt(1.21) as Q64.96
+    fn prices() -> (U160, U160) {
+        (U160::ONE << 96, U160::from(87150978765690771352898345369_u128))
+    }
+
+    #[test]
+    fn test_get_next_sqrt_price_from_input() {
+        let (price, _) = prices();
+
+        assert_eq!(
+            get_next_sqrt_price_from_input(price, ONE_ETHER, U256::from(ONE_ETHER / 10), false).unwrap(),
+            U160::from(87150978765690771352898345369_u128)
+        );
+        assert_eq!(
+            get_next_sqrt_price_from_input(price, ONE_ETHER, U256::from(ONE_ETHER / 10), true).unwrap(),
+            U160::from(72025602285694852357767227579_u128)
+        );
+        assert_eq!(get_next_sqrt_price_from_input(price, ONE_ETHER, U256::ZERO, true).unwrap(), price);
+
+        // any input that would move the price past the max fails
+        assert!(get_next_sqrt_price_from_input(U160::MAX, 1024, U256::from(1024), false).is_err());
+        assert!(get_next_sqrt_price_from_input(price, 0, U256::from(1), true).is_err());
+    }
+
+    #[test]
+    fn test_get_next_sqrt_price_from_output() {
+        let (price, _) = prices();
+
+        assert!(get_next_sqrt_price_from_output(price, 1, U256::from(ONE_ETHER), true).is_err());
+        assert!(get_next_sqrt_price_from_output(price, 1, U256::from(ONE_ETHER), false).is_err());
+        assert_eq!(get_next_sqrt_price_from_output(price, ONE_ETHER, U256::ZERO, false).unwrap(), price);
+    }
+
+    #[test]
+    fn test_get_amount_deltas() {
+        let (low, high) = prices();
+
+        assert_eq!(get_amount0_delta(low, high, ONE_ETHER, true).unwrap(), U256::from(90909090909090910_u64));
+        assert_eq!(get_amount0_delta(high, low, ONE_ETHER, false).unwrap(), U256::from(90909090909090909_u64));
+        assert_eq!(get_amount1_delta(low, high, ONE_ETHER, true).unwrap(), U256::from(100000000000000000_u64));
+        assert_eq!(get_amount1_delta(high, low, ONE_ETHER, false).unwrap(), U256::from(99999999999999999_u64));
+
+        assert_eq!(get_amount0_delta(low, low, ONE_ETHER, true).unwrap(), U256::ZERO);
+        assert!(get_amount0_delta(U160::ZERO, high, ONE_ETHER, true).is_err());
+
+        assert_eq!(
+            get_amount0_delta_signed(low, high, ONE_ETHER as i128).unwrap(),
+            -I256::try_from(90909090909090910_u64).unwrap()
+        );
+        assert_eq!(
+            get_amount1_delta_signed(low, high, -(ONE_ETHER as i128)).unwrap(),
+            I256::try_from(99999999999999999_u64).unwrap()
+        );
+    }
+}
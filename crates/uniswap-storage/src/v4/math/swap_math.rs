@@ -0,0 +1,176 @@
+use alloy_primitives::{I256, U160, U256, aliases::U24};
+
+use crate::v4::math::{
+    full_math::{mul_div, mul_div_rounding_up},
+    sqrt_price_math::{get_amount0_delta, get_amount1_delta, get_next_sqrt_price_from_input, get_next_sqrt_price_from_output}
+};
+
+/// a swap fee of 100%, in pips
+pub const MAX_SWAP_FEE: u32 = 1_000_000;
+
+/// the result of one step of a swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    /// the price after swapping the amount in/out, not to exceed the target
+    pub sqrt_price_next_x96: U160,
+    /// the amount to be swapped in, of either currency0 or currency1, not
+    /// including the fee
+    pub amount_in:           U256,
+    /// the amount to be received, of either currency0 or currency1
+    pub amount_out:          U256,
+    /// the amount of input that will be taken as a fee
+    pub fee_amount:          U256
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SwapMath.sol
+///
+/// function getSqrtPriceTarget(bool zeroForOne, uint160 sqrtPriceNextX96,
+/// uint160 sqrtPriceLimitX96) internal pure returns (uint160
+/// sqrtPriceTargetX96)
+pub fn get_sqrt_price_target(zero_for_one: bool, sqrt_price_next_x96: U160, sqrt_price_limit_x96: U160) -> U160 {
+    if zero_for_one {
+        sqrt_price_next_x96.max(sqrt_price_limit_x96)
+    } else {
+        sqrt_price_next_x96.min(sqrt_price_limit_x96)
+    }
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/SwapMath.sol
+///
+/// function computeSwapStep(uint160 sqrtPriceCurrentX96, uint160
+/// sqrtPriceTargetX96, uint128 liquidity, int256 amountRemaining, uint24
+/// feePips) internal pure returns (uint160 sqrtPriceNextX96, uint256 amountIn,
+/// uint256 amountOut, uint256 feeAmount)
+///
+/// a negative `amount_remaining` is an exact input
+pub fn compute_swap_step(
+    sqrt_price_current_x96: U160,
+    sqrt_price_target_x96: U160,
+    liquidity: u128,
+    amount_remaining: I256,
+    fee_pips: U24
+) -> eyre::Result<SwapStep> {
+    let fee_pips = U256::from(fee_pips);
+    let max_swap_fee = U256::from(MAX_SWAP_FEE);
+    // unchecked in solidity. a fee over 100% never reaches here
+    let fee_complement = max_swap_fee.wrapping_sub(fee_pips);
+
+    let zero_for_one = sqrt_price_current_x96 >= sqrt_price_target_x96;
+    let exact_in = amount_remaining.is_negative();
+
+    let sqrt_price_next_x96;
+    let mut amount_in;
+    let mut amount_out;
+    let fee_amount;
+
+    if exact_in {
+        let amount_remaining = amount_remaining.unsigned_abs();
+        let amount_remaining_less_fee = mul_div(amount_remaining, fee_complement, max_swap_fee)?;
+
+        amount_in = if zero_for_one {
+            get_amount0_delta(sqrt_price_target_x96, sqrt_price_current_x96, liquidity, true)?
+        } else {
+            get_amount1_delta(sqrt_price_current_x96, sqrt_price_target_x96, liquidity, true)?
+        };
+
+        if amount_remaining_less_fee >= amount_in {
+            // `amount_in` is capped by the target price
+            sqrt_price_next_x96 = sqrt_price_target_x96;
+            fee_amount = if fee_pips == max_swap_fee {
+                // `amount_in` is always 0 here, as `amount_remaining_less_fee`
+                // is 0
+                amount_in
+            } else {
+                mul_div_rounding_up(amount_in, fee_pips, fee_complement)?
+            };
+        } else {
+            // exhaust the remaining amount
+            amount_in = amount_remaining_less_fee;
+            sqrt_price_next_x96 =
+                get_next_sqrt_price_from_input(sqrt_price_current_x96, liquidity, amount_remaining_less_fee, zero_for_one)?;
+            // we didn't reach the target, so take the remainder of the
+            // maximum input as fee
+            fee_amount = amount_remaining - amount_in;
+        }
+
+        amount_out = if zero_for_one {
+            get_amount1_delta(sqrt_price_next_x96, sqrt_price_current_x96, liquidity, false)?
+        } else {
+            get_amount0_delta(sqrt_price_current_x96, sqrt_price_next_x96, liquidity, false)?
+        };
+    } else {
+        let amount_remaining = amount_remaining.into_raw();
+
+        amount_out = if zero_for_one {
+            get_amount1_delta(sqrt_price_target_x96, sqrt_price_current_x96, liquidity, false)?
+        } else {
+            get_amount0_delta(sqrt_price_current_x96, sqrt_price_target_x96, liquidity, false)?
+        };
+
+        if amount_remaining >= amount_out {
+            // `amount_out` is capped by the target price
+            sqrt_price_next_x96 = sqrt_price_target_x96;
+        } else {
+            // cap the output amount to not exceed the remaining output amount
+            amount_out = amount_remaining;
+            sqrt_price_next_x96 =
+                get_next_sqrt_price_from_output(sqrt_price_current_x96, liquidity, amount_out, zero_for_one)?;
+        }
+
+        amount_in = if zero_for_one {
+            get_amount0_delta(sqrt_price_next_x96, sqrt_price_current_x96, liquidity, true)?
+        } else {
+            get_amount1_delta(sqrt_price_current_x96, sqrt_price_next_x96, liquidity, true)?
+        };
+        // the fee cannot be 100% for exact out
+        fee_amount = mul_div_rounding_up(amount_in, fee_pips, fee_complement)?;
+    }
+
+    Ok(SwapStep { sqrt_price_next_x96, amount_in, amount_out, fee_amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_ETHER: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn test_compute_swap_step_capped_at_target() {
+        let price = U160::ONE << 96;
+        // sqrt(1.01)
+        let target = U160::from(79623317895830914510639640423_u128);
+
+        let expected = SwapStep {
+            sqrt_price_next_x96: target,
+            amount_in:           U256::from(9975124224178055_u64),
+            amount_out:          U256::from(9925619580021728_u64),
+            fee_amount:          U256::from(5988667735148_u64)
+        };
+
+        let amount = I256::try_from(ONE_ETHER).unwrap();
+
+        let exact_in = compute_swap_step(price, target, 2 * ONE_ETHER, -amount, U24::from(600)).unwrap();
+        assert_eq!(exact_in, expected);
+
+        let exact_out = compute_swap_step(price, target, 2 * ONE_ETHER, amount, U24::from(600)).unwrap();
+        assert_eq!(exact_out, expected);
+    }
+
+    #[test]
+    fn test_compute_swap_step_exhausts_amount() {
+        let price = U160::ONE << 96;
+        let target = U160::from(79623317895830914510639640423_u128);
+        let amount = I256::try_from(-1_000_000_i64).unwrap();
+
+        let step = compute_swap_step(price, target, 2 * ONE_ETHER, amount, U24::from(600)).unwrap();
+        assert!(step.sqrt_price_next_x96 < target);
+        assert_eq!(step.amount_in + step.fee_amount, U256::from(1_000_000));
+
+        // all of the input is the fee
+        let step = compute_swap_step(price, target, 2 * ONE_ETHER, amount, U24::from(MAX_SWAP_FEE)).unwrap();
+        assert_eq!(step.sqrt_price_next_x96, price);
+        assert_eq!(step.amount_in, U256::ZERO);
+        assert_eq!(step.fee_amount, U256::from(1_000_000));
+    }
+}
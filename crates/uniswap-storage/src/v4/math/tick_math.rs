@@ -0,0 +1,147 @@
+use alloy_primitives::{I256, U160, U256, aliases::I24, uint};
+
+pub use crate::v4::utils::{MAX_TICK, MIN_TICK};
+
+/// the price at [`MIN_TICK`]
+pub const MIN_SQRT_PRICE: U160 = uint!(4295128739_U160);
+/// the price at [`MAX_TICK`]
+pub const MAX_SQRT_PRICE: U160 = uint!(1461446703485210103287273052203988822378723970342_U160);
+
+pub const MIN_TICK_SPACING: i32 = 1;
+pub const MAX_TICK_SPACING: i32 = i16::MAX as i32;
+
+/// `1 / sqrt(1.0001) ^ (2 ^ i)` as Q128.128 for i in 1..=19. bit 0 seeds the
+/// price in [`get_sqrt_price_at_tick`]
+const SQRT_PRICE_RATIOS: [U256; 19] = [
+    uint!(0xfff97272373d413259a46990580e213a_U256),
+    uint!(0xfff2e50f5f656932ef12357cf3c7fdcc_U256),
+    uint!(0xffe5caca7e10e4e61c3624eaa0941cd0_U256),
+    uint!(0xffcb9843d60f6159c9db58835c926644_U256),
+    uint!(0xff973b41fa98c081472e6896dfb254c0_U256),
+    uint!(0xff2ea16466c96a3843ec78b326b52861_U256),
+    uint!(0xfe5dee046a99a2a811c461f1969c3053_U256),
+    uint!(0xfcbe86c7900a88aedcffc83b479aa3a4_U256),
+    uint!(0xf987a7253ac413176f2b074cf7815e54_U256),
+    uint!(0xf3392b0822b70005940c7a398e4b70f3_U256),
+    uint!(0xe7159475a2c29b7443b29c7fa6e889d9_U256),
+    uint!(0xd097f3bdfd2022b8845ad8f792aa5825_U256),
+    uint!(0xa9f746462d870fdf8a65dc1f90e061e5_U256),
+    uint!(0x70d869a156d2a1b890bb3df62baf32f7_U256),
+    uint!(0x31be135f97d08fd981231505542fcfa6_U256),
+    uint!(0x9aa508b5b7a84e1c677de54f3e99bc9_U256),
+    uint!(0x5d6af8dedb81196699c329225ee604_U256),
+    uint!(0x2216e584f5fa1ea926041bedfe98_U256),
+    uint!(0x48a170391f7dc42444e8fa2_U256)
+];
+
+const LOG_SQRT_10001_FACTOR: I256 = I256::from_raw(uint!(255738958999603826347141_U256));
+const TICK_LOW_ERROR: I256 = I256::from_raw(uint!(3402992956809132418596140100660247210_U256));
+const TICK_HIGH_ERROR: I256 = I256::from_raw(uint!(291339464771989622907027621153398088495_U256));
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/TickMath.sol
+///
+/// function getSqrtPriceAtTick(int24 tick) internal pure returns (uint160
+/// sqrtPriceX96)
+pub fn get_sqrt_price_at_tick(tick: I24) -> eyre::Result<U160> {
+    let abs_tick = tick.as_i32().unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        eyre::bail!("InvalidTick({tick})");
+    }
+
+    let mut price = if abs_tick & 0x1 != 0 {
+        uint!(0xfffcb933bd6fad37aa2d162d1a594001_U256)
+    } else {
+        U256::ONE << 128
+    };
+    for (i, ratio) in SQRT_PRICE_RATIOS.iter().enumerate() {
+        if abs_tick & (0x2 << i) != 0 {
+            price = (price * ratio) >> 128;
+        }
+    }
+
+    if tick > I24::ZERO {
+        price = U256::MAX / price;
+    }
+
+    // Q128.128 to Q64.96, rounding up so that getTickAtSqrtPrice inverts it
+    let sqrt_price_x96 = (price >> 32) + if (price & U256::from(u32::MAX)).is_zero() { U256::ZERO } else { U256::ONE };
+
+    Ok(U160::from(sqrt_price_x96))
+}
+
+/// https://github.com/Uniswap/v4-core/blob/main/src/libraries/TickMath.sol
+///
+/// function getTickAtSqrtPrice(uint160 sqrtPriceX96) internal pure returns
+/// (int24 tick)
+pub fn get_tick_at_sqrt_price(sqrt_price_x96: U160) -> eyre::Result<I24> {
+    if sqrt_price_x96 < MIN_SQRT_PRICE || sqrt_price_x96 >= MAX_SQRT_PRICE {
+        eyre::bail!("InvalidSqrtPrice({sqrt_price_x96})");
+    }
+
+    let price = U256::from(sqrt_price_x96) << 32;
+    let msb = 255 - price.leading_zeros();
+
+    let mut r = if msb >= 128 { price >> (msb - 127) } else { price << (127 - msb) };
+    let mut log_2 = (msb as i128 - 128) << 64;
+    for shift in (50..64).rev() {
+        r = (r * r) >> 127;
+        let f = (r >> 128).to::<usize>();
+        // the low 64 bits of `log_2` start out clear, so or-ing the bit in is
+        // adding it, whatever the sign
+        log_2 += (f as i128) << shift;
+        r >>= f;
+    }
+
+    // Q128.128
+    let log_sqrt10001 = I256::unchecked_from(log_2) * LOG_SQRT_10001_FACTOR;
+
+    let tick_low = I24::unchecked_from((log_sqrt10001 - TICK_LOW_ERROR).asr(128).as_i32());
+    let tick_high = I24::unchecked_from((log_sqrt10001 + TICK_HIGH_ERROR).asr(128).as_i32());
+
+    if tick_low == tick_high || get_sqrt_price_at_tick(tick_high)? > sqrt_price_x96 {
+        Ok(tick_low)
+    } else {
+        Ok(tick_high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(tick: i32) -> I24 {
+        I24::unchecked_from(tick)
+    }
+
+    #[test]
+    fn test_get_sqrt_price_at_tick() {
+        assert_eq!(get_sqrt_price_at_tick(tick(0)).unwrap(), U160::ONE << 96);
+        assert_eq!(get_sqrt_price_at_tick(tick(MIN_TICK)).unwrap(), MIN_SQRT_PRICE);
+        assert_eq!(get_sqrt_price_at_tick(tick(MAX_TICK)).unwrap(), MAX_SQRT_PRICE);
+        assert_eq!(get_sqrt_price_at_tick(tick(1)).unwrap(), U160::from(79232123823359799118286999568_u128));
+        assert_eq!(get_sqrt_price_at_tick(tick(-1)).unwrap(), U160::from(79224201403219477170569942574_u128));
+        assert_eq!(
+            get_sqrt_price_at_tick(tick(191000)).unwrap(),
+            U160::from_str_radix("1112204183004146427810822122750822", 10).unwrap()
+        );
+
+        assert!(get_sqrt_price_at_tick(tick(MIN_TICK - 1)).is_err());
+        assert!(get_sqrt_price_at_tick(tick(MAX_TICK + 1)).is_err());
+    }
+
+    #[test]
+    fn test_get_tick_at_sqrt_price() {
+        assert_eq!(get_tick_at_sqrt_price(MIN_SQRT_PRICE).unwrap(), tick(MIN_TICK));
+        assert_eq!(get_tick_at_sqrt_price(MAX_SQRT_PRICE - U160::ONE).unwrap(), tick(MAX_TICK - 1));
+        assert_eq!(get_tick_at_sqrt_price(U160::ONE << 96).unwrap(), tick(0));
+
+        for t in [1, -1, 50, -50, 191000, -191000, MAX_TICK - 1] {
+            let sqrt_price = get_sqrt_price_at_tick(tick(t)).unwrap();
+            assert_eq!(get_tick_at_sqrt_price(sqrt_price).unwrap(), tick(t));
+            assert_eq!(get_tick_at_sqrt_price(sqrt_price - U160::ONE).unwrap(), tick(t - 1));
+        }
+
+        assert!(get_tick_at_sqrt_price(MIN_SQRT_PRICE - U160::ONE).is_err());
+        assert!(get_tick_at_sqrt_price(MAX_SQRT_PRICE).is_err());
+    }
+}
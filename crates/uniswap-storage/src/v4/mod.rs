@@ -1,7 +1,14 @@
 mod common;
 pub use common::*;
 mod constants;
+pub mod math;
 pub mod pool_manager;
 pub mod position_manager;
+mod snapshot;
+pub use snapshot::*;
+mod swap;
+pub use swap::*;
+mod tracker;
+pub use tracker::*;
 pub mod utils;
 pub use constants::*;
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use alloy_eips::BlockId;
 use alloy_primitives::{Address, B256, I64, U256, aliases::I24};
 use serde::{Deserialize, Serialize};
@@ -104,14 +106,28 @@ pub async fn next_initialized_tick_within_one_word<F: StorageSlotFetcher>(
     lte: bool,
     block_id: BlockId
 ) -> eyre::Result<(I24, bool)> {
+    let compressed = compress_tick(tick, tick_spacing) + if lte { I24::ZERO } else { I24::ONE };
+    let (word_pos, _) = _tick_position_from_compressed(compressed);
+    let bitmap = tick_bitmap_from_word(slot_fetcher, pool_manager_address, pool_id, word_pos, block_id).await?;
+
+    Ok(next_initialized_tick_within_loaded_word(&HashMap::from([(word_pos, bitmap)]), tick, tick_spacing, lte))
+}
+
+/// [`next_initialized_tick_within_one_word`] over words that are already
+/// loaded. a word missing from `words` reads as empty
+pub fn next_initialized_tick_within_loaded_word(
+    words: &HashMap<i16, TickBitmap>,
+    tick: I24,
+    tick_spacing: I24,
+    lte: bool
+) -> (I24, bool) {
+    let word = |word_pos: i16| words.get(&word_pos).map_or(U256::ZERO, |bitmap| bitmap.0);
+
     let mut compressed = compress_tick(tick, tick_spacing);
     if lte {
         let (word_pos, bit_pos) = _tick_position_from_compressed(compressed);
         let mask = U256::MAX >> (U256::from(u8::MAX) - U256::from(bit_pos));
-        let masked = tick_bitmap_from_word(slot_fetcher, pool_manager_address, pool_id, word_pos, block_id)
-            .await?
-            .0
-            & mask;
+        let masked = word(word_pos) & mask;
 
         let initialized = masked != U256::ZERO;
         let next = if initialized {
@@ -119,15 +135,12 @@ pub async fn next_initialized_tick_within_one_word<F: StorageSlotFetcher>(
         } else {
             (compressed - I24::unchecked_from(bit_pos)) * tick_spacing
         };
-        Ok((next, initialized))
+        (next, initialized)
     } else {
         compressed += I24::ONE;
         let (word_pos, bit_pos) = _tick_position_from_compressed(compressed);
         let mask = !((U256::ONE << bit_pos) - U256::ONE);
-        let masked = tick_bitmap_from_word(slot_fetcher, pool_manager_address, pool_id, word_pos, block_id)
-            .await?
-            .0
-            & mask;
+        let masked = word(word_pos) & mask;
 
         let initialized = masked != U256::ZERO;
         let next = if initialized {
@@ -137,7 +150,7 @@ pub async fn next_initialized_tick_within_one_word<F: StorageSlotFetcher>(
         } else {
             (compressed + I24::unchecked_from(u8::MAX - bit_pos)) * tick_spacing
         };
-        Ok((next, initialized))
+        (next, initialized)
     }
 }
 
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use alloy_eips::{BlockId, BlockNumHash};
+use alloy_primitives::{Address, B256, U256, aliases::I24};
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BlockNumHashFetcher, StorageSlotFetcher,
+    types::TickData,
+    v4::{
+        UnpackedSlot0, V4PoolKey, V4PoolState,
+        pool_manager::{
+            pool_state::{pool_manager_pool_fee_growth_global, pool_manager_pool_liquidity, pool_manager_pool_slot0},
+            pool_tick_state::pool_manager_load_tick_data,
+            tick_bitmap::{TickBitmap, compress_tick, tick_bitmap_from_word, tick_from_word_and_bit_pos}
+        },
+        utils::{MAX_TICK, MIN_TICK}
+    }
+};
+
+/// the most storage reads a snapshot has in flight at once. low enough that
+/// public rpc endpoints don't rate limit the thousands of bitmap words of a
+/// pool with a small tick spacing
+const MAX_CONCURRENT_READS: usize = 32;
+
+/// everything a v4 pool keeps in the `PoolManager` that swaps and liquidity
+/// changes touch, read at one block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct V4PoolSnapshot {
+    pub block_number:            u64,
+    pub block_hash:              B256,
+    pub pool_key:                V4PoolKey,
+    pub slot0:                   UnpackedSlot0,
+    pub liquidity:               u128,
+    pub fee_growth_global0_x128: U256,
+    pub fee_growth_global1_x128: U256,
+    /// the words of the tick bitmap with any tick initialized
+    pub tick_bitmap:             HashMap<i16, TickBitmap>,
+    /// the initialized ticks
+    pub ticks:                   HashMap<I24, TickData>
+}
+
+impl V4PoolSnapshot {
+    /// resolves `block_id` first and reads everything at its hash, so the
+    /// snapshot is consistent even if `block_id` is a tag that moves while it
+    /// loads. every word of the bitmap is read, then every tick it has set
+    pub async fn load<F>(fetcher: &F, pool_manager: Address, pool_key: V4PoolKey, block_id: BlockId) -> eyre::Result<Self>
+    where
+        F: StorageSlotFetcher + BlockNumHashFetcher
+    {
+        let block = fetcher.block_num_hash(block_id).await?;
+        let pinned = BlockId::hash(block.hash);
+        let pool_id = B256::from(pool_key);
+        let tick_spacing = pool_key.tickSpacing;
+
+        let (slot0, liquidity, (fee_growth_global0_x128, fee_growth_global1_x128)) = futures::try_join!(
+            pool_manager_pool_slot0(fetcher, pool_manager, pool_id, pinned),
+            pool_manager_pool_liquidity(fetcher, pool_manager, pool_id, pinned),
+            pool_manager_pool_fee_growth_global(fetcher, pool_manager, pool_id, pinned)
+        )?;
+
+        let min_word = compress_tick(I24::unchecked_from(MIN_TICK), tick_spacing).as_i32() >> 8;
+        let max_word = compress_tick(I24::unchecked_from(MAX_TICK), tick_spacing).as_i32() >> 8;
+        let tick_bitmap = futures::stream::iter(min_word..=max_word)
+            .map(|word_pos| async move {
+                let word_pos = word_pos as i16;
+                tick_bitmap_from_word(fetcher, pool_manager, pool_id, word_pos, pinned)
+                    .await
+                    .map(|bitmap| (word_pos, bitmap))
+            })
+            .buffer_unordered(MAX_CONCURRENT_READS)
+            .try_filter(|(_, bitmap)| futures::future::ready(bitmap.0 != U256::ZERO))
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+
+        let initialized_ticks = tick_bitmap
+            .iter()
+            .flat_map(|(word_pos, bitmap)| {
+                (0..=u8::MAX)
+                    .filter(|bit_pos| bitmap.is_initialized(*bit_pos))
+                    .map(|bit_pos| tick_from_word_and_bit_pos(*word_pos, bit_pos, tick_spacing))
+            })
+            .collect::<Vec<_>>();
+        let ticks = futures::stream::iter(initialized_ticks)
+            .map(|tick| pool_manager_load_tick_data(fetcher, pool_manager, tick_spacing, pool_id, tick, pinned))
+            .buffer_unordered(MAX_CONCURRENT_READS)
+            .map_ok(|tick| (tick.tick, tick))
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+
+        Ok(Self {
+            block_number: block.number,
+            block_hash: block.hash,
+            pool_key,
+            slot0,
+            liquidity: (liquidity & U256::from(u128::MAX)).to::<u128>(),
+            fee_growth_global0_x128,
+            fee_growth_global1_x128,
+            tick_bitmap,
+            ticks
+        })
+    }
+
+    pub fn block(&self) -> BlockNumHash {
+        BlockNumHash::new(self.block_number, self.block_hash)
+    }
+
+    pub fn pool_id(&self) -> B256 {
+        self.pool_key.into()
+    }
+
+    /// the pool as of the snapshot, to simulate swaps on. every tick was
+    /// loaded, so swaps are only bounded by the min and max tick
+    pub fn pool_state(&self) -> V4PoolState {
+        V4PoolState {
+            tick_spacing:            self.pool_key.tickSpacing,
+            slot0:                   self.slot0,
+            liquidity:               self.liquidity,
+            fee_growth_global0_x128: self.fee_growth_global0_x128,
+            fee_growth_global1_x128: self.fee_growth_global1_x128,
+            ticks:                   self.ticks.clone(),
+            tick_bitmap:             self.tick_bitmap.clone(),
+            loaded_ticks:            I24::unchecked_from(MIN_TICK)..=I24::unchecked_from(MAX_TICK)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::aliases::U24;
+
+    use super::*;
+    use crate::{
+        angstrom::mainnet::ANGSTROM_L1_CONSTANTS_MAINNET,
+        test_utils::*,
+        v4::{UNISWAP_V4_CONSTANTS_MAINNET, pool_manager::pool_tick_state::pool_manager_load_tick_map}
+    };
+
+    #[tokio::test]
+    async fn test_v4_pool_snapshot() {
+        let provider = eth_provider().await;
+        let block_number = 23998000;
+
+        let pool_key = V4PoolKey {
+            currency0:   USDC,
+            currency1:   WETH,
+            fee:         U24::from(0x800000),
+            tickSpacing: I24::unchecked_from(10),
+            hooks:       ANGSTROM_L1_CONSTANTS_MAINNET.angstrom_address()
+        };
+        let pool_manager = UNISWAP_V4_CONSTANTS_MAINNET.pool_manager();
+
+        let snapshot = V4PoolSnapshot::load(&provider, pool_manager, pool_key, BlockId::number(block_number))
+            .await
+            .unwrap();
+        assert_eq!(snapshot.block_number, block_number);
+        assert_eq!(snapshot.fee_growth_global0_x128, U256::from(13180763546271931776686481343917_u128));
+        assert_eq!(
+            snapshot.tick_bitmap[&346].0,
+            U256::from_str_radix("2854495385411919762116571938898990272765493248", 10).unwrap()
+        );
+
+        let tick_map = pool_manager_load_tick_map(
+            &provider,
+            pool_manager,
+            snapshot.pool_id(),
+            pool_key.tickSpacing,
+            None,
+            None,
+            BlockId::number(block_number)
+        )
+        .await
+        .unwrap();
+        let initialized = tick_map
+            .into_values()
+            .filter(|tick| tick.is_initialized)
+            .map(|tick| (tick.tick, tick))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(snapshot.ticks, initialized);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: V4PoolSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.block(), snapshot.block());
+        assert_eq!(decoded.slot0, snapshot.slot0);
+        assert_eq!(decoded.ticks, snapshot.ticks);
+    }
+}
@@ -0,0 +1,712 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use alloy_primitives::{
+    I256, U160, U256,
+    aliases::{I24, U24}
+};
+
+use crate::{
+    types::TickData,
+    v4::{
+        UnpackedSlot0,
+        math::{
+            fees::{PIPS_DENOMINATOR, calculate_swap_fee, get_one_for_zero_fee, get_zero_for_one_fee, validate_lp_fee},
+            full_math::simple_mul_div,
+            liquidity_math::add_delta,
+            sqrt_price_math::to_int256,
+            swap_math::{MAX_SWAP_FEE, compute_swap_step, get_sqrt_price_target},
+            tick_math::{MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE, MIN_TICK, get_sqrt_price_at_tick, get_tick_at_sqrt_price}
+        },
+        pool_manager::tick_bitmap::{TickBitmap, next_initialized_tick_within_loaded_word, tick_position_from_compressed},
+        utils::FIXED_POINT_128
+    }
+};
+
+/// the state of a v4 pool that swaps read and write, held in memory
+#[derive(Debug, Clone)]
+pub struct V4PoolState {
+    pub tick_spacing:            I24,
+    pub slot0:                   UnpackedSlot0,
+    pub liquidity:               u128,
+    pub fee_growth_global0_x128: U256,
+    pub fee_growth_global1_x128: U256,
+    /// the initialized ticks
+    pub ticks:                   HashMap<I24, TickData>,
+    pub tick_bitmap:             HashMap<i16, TickBitmap>,
+    /// the ticks every initialized one of is in `ticks`. swaps that would
+    /// step past it fail, as the ticks beyond it are unknown
+    pub loaded_ticks:            RangeInclusive<I24>
+}
+
+/// an initialized tick a swap crossed, with the global fee growths it was
+/// crossed at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V4TickCrossing {
+    pub tick:                    I24,
+    pub fee_growth_global0_x128: U256,
+    pub fee_growth_global1_x128: U256
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V4SwapResult {
+    pub zero_for_one:           bool,
+    /// the balance change of the swapper in currency0, negative when paid to
+    /// the pool. hook deltas are not included
+    pub amount0:                i128,
+    /// the balance change of the swapper in currency1
+    pub amount1:                i128,
+    pub sqrt_price_x96:         U160,
+    pub tick:                   I24,
+    pub liquidity:              u128,
+    /// the lp fee with the protocol fee taken on top, in pips
+    pub swap_fee:               U24,
+    /// the part of the fees owed to the protocol, in the input currency
+    pub amount_to_protocol:     U256,
+    /// the global fee growth of the input currency after the swap
+    pub fee_growth_global_x128: U256,
+    /// in the order they were crossed
+    pub crossed_ticks:          Vec<V4TickCrossing>
+}
+
+impl V4PoolState {
+    /// `ticks` as loaded by
+    /// [`pool_manager_load_tick_map`](crate::v4::pool_manager::pool_tick_state::pool_manager_load_tick_map)
+    /// over `loaded_ticks`. the tick bitmap is rebuilt from the initialized
+    /// ones, so swaps are bounded to `loaded_ticks`
+    pub fn new(
+        tick_spacing: I24,
+        slot0: UnpackedSlot0,
+        liquidity: u128,
+        fee_growth_global: (U256, U256),
+        loaded_ticks: RangeInclusive<I24>,
+        ticks: impl IntoIterator<Item = TickData>
+    ) -> Self {
+        let mut this = Self {
+            tick_spacing,
+            slot0,
+            liquidity,
+            fee_growth_global0_x128: fee_growth_global.0,
+            fee_growth_global1_x128: fee_growth_global.1,
+            ticks: HashMap::new(),
+            tick_bitmap: HashMap::new(),
+            loaded_ticks
+        };
+
+        for tick in ticks.into_iter().filter(|tick| tick.is_initialized) {
+            let (word_pos, bit_pos) = tick_position_from_compressed(tick.tick, tick_spacing);
+            this.tick_bitmap
+                .entry(word_pos)
+                .or_insert(TickBitmap(U256::ZERO))
+                .0 |= U256::ONE << bit_pos;
+            this.ticks.insert(tick.tick, tick);
+        }
+
+        this
+    }
+
+    /// https://github.com/Uniswap/v4-core/blob/main/src/libraries/Pool.sol
+    ///
+    /// function swap(State storage self, SwapParams memory params) internal
+    /// returns (BalanceDelta swapDelta, uint256 amountToProtocol, uint24
+    /// swapFee, SwapResult memory result)
+    ///
+    /// a negative `amount_specified` is an exact input. the pool is left
+    /// untouched, see [`Self::apply_swap`]. fails if the swap would move the
+    /// price out of [`Self::loaded_ticks`]
+    pub fn simulate_swap(
+        &self,
+        amount_specified: I256,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: U160
+    ) -> eyre::Result<V4SwapResult> {
+        self.simulate_swap_with_lp_fee_override(amount_specified, zero_for_one, sqrt_price_limit_x96, None)
+    }
+
+    /// [`Self::simulate_swap`] with the lp fee a dynamic fee pool's
+    /// `beforeSwap` hook returned, without the override flag. the lp fee in
+    /// slot0 is used otherwise
+    pub fn simulate_swap_with_lp_fee_override(
+        &self,
+        amount_specified: I256,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: U160,
+        lp_fee_override: Option<U24>
+    ) -> eyre::Result<V4SwapResult> {
+        let slot0 = self.slot0;
+        let protocol_fee = if zero_for_one {
+            get_zero_for_one_fee(slot0.protocol_fee)
+        } else {
+            get_one_for_zero_fee(slot0.protocol_fee)
+        };
+
+        let lp_fee = match lp_fee_override {
+            Some(lp_fee) => {
+                validate_lp_fee(lp_fee)?;
+                lp_fee
+            }
+            None => slot0.lp_fee
+        };
+        let swap_fee = if protocol_fee == 0 { lp_fee } else { calculate_swap_fee(protocol_fee, lp_fee) };
+
+        // a swap fee of 100% makes exact output swaps impossible since the
+        // input is entirely consumed by the fee
+        let exact_out = amount_specified.is_positive();
+        if swap_fee.to::<u32>() >= MAX_SWAP_FEE && exact_out {
+            eyre::bail!("InvalidFeeForExactOut");
+        }
+
+        let mut result = V4SwapResult {
+            zero_for_one,
+            amount0: 0,
+            amount1: 0,
+            sqrt_price_x96: slot0.sqrt_price_x96,
+            tick: slot0.tick,
+            liquidity: self.liquidity,
+            swap_fee,
+            amount_to_protocol: U256::ZERO,
+            fee_growth_global_x128: if zero_for_one { self.fee_growth_global0_x128 } else { self.fee_growth_global1_x128 },
+            crossed_ticks: Vec::new()
+        };
+
+        if amount_specified.is_zero() {
+            return Ok(result);
+        }
+
+        if zero_for_one {
+            if sqrt_price_limit_x96 >= slot0.sqrt_price_x96 {
+                eyre::bail!("PriceLimitAlreadyExceeded({}, {sqrt_price_limit_x96})", slot0.sqrt_price_x96);
+            }
+            // swaps can never occur at MIN_TICK, only at MIN_TICK + 1
+            if sqrt_price_limit_x96 <= MIN_SQRT_PRICE {
+                eyre::bail!("PriceLimitOutOfBounds({sqrt_price_limit_x96})");
+            }
+        } else {
+            if sqrt_price_limit_x96 <= slot0.sqrt_price_x96 {
+                eyre::bail!("PriceLimitAlreadyExceeded({}, {sqrt_price_limit_x96})", slot0.sqrt_price_x96);
+            }
+            if sqrt_price_limit_x96 >= MAX_SQRT_PRICE {
+                eyre::bail!("PriceLimitOutOfBounds({sqrt_price_limit_x96})");
+            }
+        }
+
+        let min_tick = I24::unchecked_from(MIN_TICK);
+        let max_tick = I24::unchecked_from(MAX_TICK);
+        let (loaded_start, loaded_end) = (*self.loaded_ticks.start(), *self.loaded_ticks.end());
+        let protocol_fee = U256::from(protocol_fee);
+
+        let mut amount_specified_remaining = amount_specified;
+        let mut amount_calculated = I256::ZERO;
+
+        while !(amount_specified_remaining.is_zero() || result.sqrt_price_x96 == sqrt_price_limit_x96) {
+            let sqrt_price_start_x96 = result.sqrt_price_x96;
+
+            // the next step searches from the current tick down for zero for
+            // one swaps and from the one above it up otherwise
+            let within_loaded_ticks = if zero_for_one {
+                self.loaded_ticks.contains(&result.tick)
+            } else {
+                loaded_start - I24::ONE <= result.tick && result.tick < loaded_end
+            };
+            if !within_loaded_ticks {
+                eyre::bail!("the swap leaves the loaded ticks [{loaded_start}, {loaded_end}] at tick {}", result.tick);
+            }
+
+            let (mut tick_next, mut initialized) =
+                next_initialized_tick_within_loaded_word(&self.tick_bitmap, result.tick, self.tick_spacing, zero_for_one);
+            // the tick bitmap is not aware of the min/max tick
+            if tick_next <= min_tick {
+                tick_next = min_tick;
+            }
+            if tick_next >= max_tick {
+                tick_next = max_tick;
+            }
+            // or of the ticks that were loaded. a step stops at their edge and
+            // the next one fails
+            if !self.loaded_ticks.contains(&tick_next) {
+                tick_next = tick_next.clamp(loaded_start, loaded_end);
+                initialized = false;
+            }
+
+            let sqrt_price_next_x96 = get_sqrt_price_at_tick(tick_next)?;
+            let step = compute_swap_step(
+                result.sqrt_price_x96,
+                get_sqrt_price_target(zero_for_one, sqrt_price_next_x96, sqrt_price_limit_x96),
+                result.liquidity,
+                amount_specified_remaining,
+                swap_fee
+            )?;
+            result.sqrt_price_x96 = step.sqrt_price_next_x96;
+            let mut fee_amount = step.fee_amount;
+
+            let amount_in_with_fee = step
+                .amount_in
+                .checked_add(fee_amount)
+                .ok_or_else(|| eyre::eyre!("arithmetic overflow"))?;
+            if exact_out {
+                amount_specified_remaining = amount_specified_remaining.wrapping_sub(to_int256(step.amount_out)?);
+                amount_calculated = amount_calculated
+                    .checked_sub(to_int256(amount_in_with_fee)?)
+                    .ok_or_else(|| eyre::eyre!("arithmetic overflow"))?;
+            } else {
+                amount_specified_remaining = amount_specified_remaining.wrapping_add(to_int256(amount_in_with_fee)?);
+                amount_calculated = amount_calculated
+                    .checked_add(to_int256(step.amount_out)?)
+                    .ok_or_else(|| eyre::eyre!("arithmetic overflow"))?;
+            }
+
+            if !protocol_fee.is_zero() {
+                // rounds down to favor lps over the protocol
+                let delta = if U256::from(swap_fee) == protocol_fee {
+                    // the lp fee is 0, so the entire fee is owed to the protocol
+                    fee_amount
+                } else {
+                    amount_in_with_fee.wrapping_mul(protocol_fee) / U256::from(PIPS_DENOMINATOR)
+                };
+                fee_amount = fee_amount.wrapping_sub(delta);
+                result.amount_to_protocol = result.amount_to_protocol.wrapping_add(delta);
+            }
+
+            if result.liquidity > 0 {
+                let fee_growth = simple_mul_div(
+                    fee_amount,
+                    U256::from_be_bytes(FIXED_POINT_128.0),
+                    U256::from(result.liquidity)
+                );
+                result.fee_growth_global_x128 = result.fee_growth_global_x128.wrapping_add(fee_growth);
+            }
+
+            // shift the tick if we reached the next price, preemptively
+            // decrementing it for zero for one swaps
+            if result.sqrt_price_x96 == sqrt_price_next_x96 {
+                if initialized {
+                    let (fee_growth_global0_x128, fee_growth_global1_x128) = if zero_for_one {
+                        (result.fee_growth_global_x128, self.fee_growth_global1_x128)
+                    } else {
+                        (self.fee_growth_global0_x128, result.fee_growth_global_x128)
+                    };
+                    let tick = self
+                        .ticks
+                        .get(&tick_next)
+                        .ok_or_else(|| eyre::eyre!("tick {tick_next} is initialized but was not loaded"))?;
+
+                    // moving leftward, liquidity net is interpreted with the
+                    // opposite sign
+                    let liquidity_net = if zero_for_one { tick.liquidity_net.wrapping_neg() } else { tick.liquidity_net };
+                    result.liquidity = add_delta(result.liquidity, liquidity_net)?;
+                    result.crossed_ticks.push(V4TickCrossing {
+                        tick: tick_next,
+                        fee_growth_global0_x128,
+                        fee_growth_global1_x128
+                    });
+                }
+
+                result.tick = if zero_for_one { tick_next - I24::ONE } else { tick_next };
+            } else if result.sqrt_price_x96 != sqrt_price_start_x96 {
+                // recompute unless we're on a lower tick boundary and haven't
+                // moved
+                result.tick = get_tick_at_sqrt_price(result.sqrt_price_x96)?;
+            }
+        }
+
+        let amount_specified_used = amount_specified.wrapping_sub(amount_specified_remaining);
+        // currency1 is the specified currency
+        let (amount0, amount1) = if zero_for_one != amount_specified.is_negative() {
+            (amount_calculated, amount_specified_used)
+        } else {
+            (amount_specified_used, amount_calculated)
+        };
+        result.amount0 = to_int128(amount0)?;
+        result.amount1 = to_int128(amount1)?;
+
+        Ok(result)
+    }
+
+    /// writes the state `result` left the pool in. `result` must have been
+    /// simulated on this state
+    pub fn apply_swap(&mut self, result: &V4SwapResult) {
+        self.slot0.sqrt_price_x96 = result.sqrt_price_x96;
+        self.slot0.tick = result.tick;
+        self.liquidity = result.liquidity;
+
+        if result.zero_for_one {
+            self.fee_growth_global0_x128 = result.fee_growth_global_x128;
+        } else {
+            self.fee_growth_global1_x128 = result.fee_growth_global_x128;
+        }
+
+        // Pool.crossTick
+        for crossing in &result.crossed_ticks {
+            if let Some(tick) = self.ticks.get_mut(&crossing.tick) {
+                tick.fee_growth_outside0_x128 = crossing
+                    .fee_growth_global0_x128
+                    .wrapping_sub(tick.fee_growth_outside0_x128);
+                tick.fee_growth_outside1_x128 = crossing
+                    .fee_growth_global1_x128
+                    .wrapping_sub(tick.fee_growth_outside1_x128);
+            }
+        }
+    }
+
+    /// [`Self::simulate_swap_with_lp_fee_override`], applied to the pool
+    pub fn swap(
+        &mut self,
+        amount_specified: I256,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: U160,
+        lp_fee_override: Option<U24>
+    ) -> eyre::Result<V4SwapResult> {
+        let result =
+            self.simulate_swap_with_lp_fee_override(amount_specified, zero_for_one, sqrt_price_limit_x96, lp_fee_override)?;
+        self.apply_swap(&result);
+
+        Ok(result)
+    }
+
+    /// https://github.com/Uniswap/v4-core/blob/main/src/libraries/Pool.sol
+    ///
+    /// function modifyLiquidity(State storage self, ModifyLiquidityParams
+    /// memory params) internal returns (BalanceDelta delta, BalanceDelta
+    /// feeDelta)
+    ///
+    /// only the ticks, the tick bitmap and the active liquidity are updated,
+    /// positions are not kept
+    pub fn modify_liquidity(&mut self, tick_lower: I24, tick_upper: I24, liquidity_delta: i128) -> eyre::Result<()> {
+        if liquidity_delta == 0 {
+            return Ok(());
+        }
+
+        let flipped_lower = self.update_tick(tick_lower, liquidity_delta, false)?;
+        let flipped_upper = self.update_tick(tick_upper, liquidity_delta, true)?;
+
+        if flipped_lower {
+            self.flip_tick(tick_lower)?;
+        }
+        if flipped_upper {
+            self.flip_tick(tick_upper)?;
+        }
+
+        // clear any tick data that is no longer needed
+        if liquidity_delta < 0 {
+            if flipped_lower {
+                self.ticks.remove(&tick_lower);
+            }
+            if flipped_upper {
+                self.ticks.remove(&tick_upper);
+            }
+        }
+
+        if tick_lower <= self.slot0.tick && self.slot0.tick < tick_upper {
+            self.liquidity = add_delta(self.liquidity, liquidity_delta)?;
+        }
+
+        Ok(())
+    }
+
+    /// https://github.com/Uniswap/v4-core/blob/main/src/libraries/Pool.sol
+    ///
+    /// function donate(State storage state, uint256 amount0, uint256 amount1)
+    /// internal returns (BalanceDelta delta)
+    pub fn donate(&mut self, amount0: U256, amount1: U256) -> eyre::Result<()> {
+        if self.liquidity == 0 {
+            eyre::bail!("NoLiquidityToReceiveFees");
+        }
+
+        let q128 = U256::from_be_bytes(FIXED_POINT_128.0);
+        let liquidity = U256::from(self.liquidity);
+        if !amount0.is_zero() {
+            self.fee_growth_global0_x128 = self
+                .fee_growth_global0_x128
+                .wrapping_add(simple_mul_div(amount0, q128, liquidity));
+        }
+        if !amount1.is_zero() {
+            self.fee_growth_global1_x128 = self
+                .fee_growth_global1_x128
+                .wrapping_add(simple_mul_div(amount1, q128, liquidity));
+        }
+
+        Ok(())
+    }
+
+    /// https://github.com/Uniswap/v4-core/blob/main/src/libraries/Pool.sol
+    ///
+    /// function updateTick(State storage self, int24 tick, int128
+    /// liquidityDelta, bool upper) internal returns (bool flipped, uint128
+    /// liquidityGrossAfter)
+    fn update_tick(&mut self, tick: I24, liquidity_delta: i128, upper: bool) -> eyre::Result<bool> {
+        let current_tick = self.slot0.tick;
+        let (fee_growth_global0_x128, fee_growth_global1_x128) =
+            (self.fee_growth_global0_x128, self.fee_growth_global1_x128);
+
+        let info = self.ticks.entry(tick).or_insert(TickData {
+            tick,
+            is_initialized: false,
+            liquidity_net: 0,
+            liquidity_gross: 0,
+            fee_growth_outside0_x128: U256::ZERO,
+            fee_growth_outside1_x128: U256::ZERO
+        });
+
+        let liquidity_gross_before = info.liquidity_gross;
+        let liquidity_gross_after = add_delta(liquidity_gross_before, liquidity_delta)?;
+        let flipped = (liquidity_gross_after == 0) != (liquidity_gross_before == 0);
+
+        // by convention, we assume that all growth before a tick was
+        // initialized happened below the tick
+        if liquidity_gross_before == 0 && tick <= current_tick {
+            info.fee_growth_outside0_x128 = fee_growth_global0_x128;
+            info.fee_growth_outside1_x128 = fee_growth_global1_x128;
+        }
+
+        // when the lower (upper) tick is crossed left to right, liquidity
+        // must be added (removed)
+        let liquidity_net = if upper {
+            info.liquidity_net.checked_sub(liquidity_delta)
+        } else {
+            info.liquidity_net.checked_add(liquidity_delta)
+        };
+        info.liquidity_net = liquidity_net.ok_or_else(|| eyre::eyre!("arithmetic overflow"))?;
+        info.liquidity_gross = liquidity_gross_after;
+        info.is_initialized = liquidity_gross_after != 0;
+
+        Ok(flipped)
+    }
+
+    /// https://github.com/Uniswap/v4-core/blob/main/src/libraries/TickBitmap.sol
+    ///
+    /// function flipTick(mapping(int16 => uint256) storage self, int24 tick,
+    /// int24 tickSpacing) internal
+    fn flip_tick(&mut self, tick: I24) -> eyre::Result<()> {
+        if tick % self.tick_spacing != I24::ZERO {
+            eyre::bail!("TickMisaligned({tick}, {})", self.tick_spacing);
+        }
+
+        let (word_pos, bit_pos) = tick_position_from_compressed(tick, self.tick_spacing);
+        let word = self
+            .tick_bitmap
+            .entry(word_pos)
+            .or_insert(TickBitmap(U256::ZERO));
+        word.0 ^= U256::ONE << bit_pos;
+        // only words with a tick initialized are kept
+        if word.0.is_zero() {
+            self.tick_bitmap.remove(&word_pos);
+        }
+
+        Ok(())
+    }
+}
+
+/// `SafeCast.toInt128`
+fn to_int128(value: I256) -> eyre::Result<i128> {
+    i128::try_from(value).map_err(|_| eyre::eyre!("SafeCastOverflow: {value} is not an int128"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_ETHER: u128 = 1_000_000_000_000_000_000;
+
+    /// one position of 1e18 liquidity over [-600, 600], at price 1
+    fn pool(lp_fee: u32, protocol_fee: u32) -> V4PoolState {
+        let tick = |tick: i32, liquidity_net: i128| TickData {
+            tick:                     I24::unchecked_from(tick),
+            is_initialized:           true,
+            liquidity_net,
+            liquidity_gross:          ONE_ETHER,
+            fee_growth_outside0_x128: U256::ZERO,
+            fee_growth_outside1_x128: U256::ZERO
+        };
+        let slot0 = UnpackedSlot0 {
+            sqrt_price_x96: U160::ONE << 96,
+            tick:           I24::ZERO,
+            protocol_fee:   U24::from(protocol_fee),
+            lp_fee:         U24::from(lp_fee)
+        };
+
+        V4PoolState::new(
+            I24::unchecked_from(60),
+            slot0,
+            ONE_ETHER,
+            (U256::ZERO, U256::ZERO),
+            I24::unchecked_from(MIN_TICK)..=I24::unchecked_from(MAX_TICK),
+            [tick(-600, ONE_ETHER as i128), tick(600, -(ONE_ETHER as i128))]
+        )
+    }
+
+    fn amount(amount: i128) -> I256 {
+        I256::try_from(amount).unwrap()
+    }
+
+    #[test]
+    fn test_simulate_swap_within_range() {
+        let pool = pool(3000, 0);
+
+        let exact_in = pool
+            .simulate_swap(amount(-1_000_000_000_000_000), true, MIN_SQRT_PRICE + U160::ONE)
+            .unwrap();
+        assert_eq!(exact_in.amount0, -1_000_000_000_000_000);
+        assert_eq!(exact_in.amount1, 996006981039903);
+        assert_eq!(exact_in.sqrt_price_x96, U160::from(79149250711305166342700278159_u128));
+        assert_eq!(exact_in.tick, I24::unchecked_from(-20));
+        assert_eq!(exact_in.liquidity, ONE_ETHER);
+        assert_eq!(exact_in.swap_fee, U24::from(3000));
+        assert_eq!(
+            exact_in.fee_growth_global_x128,
+            U256::from_str_radix("1020847100762815390390123822295304", 10).unwrap()
+        );
+        assert!(exact_in.crossed_ticks.is_empty());
+
+        let exact_out = pool
+            .simulate_swap(amount(1_000_000_000_000_000), false, MAX_SQRT_PRICE - U160::ONE)
+            .unwrap();
+        assert_eq!(exact_out.amount0, 1_000_000_000_000_000);
+        assert_eq!(exact_out.amount1, -1004013040121367);
+        assert_eq!(exact_out.sqrt_price_x96, U160::from(79307469984248586179723674011_u128));
+        assert_eq!(exact_out.tick, I24::unchecked_from(20));
+    }
+
+    #[test]
+    fn test_simulate_swap_protocol_fee() {
+        let pool = pool(3000, (500 << 12) | 1000);
+
+        let zero_for_one = pool
+            .simulate_swap(amount(-1_000_000_000_000_000), true, MIN_SQRT_PRICE + U160::ONE)
+            .unwrap();
+        assert_eq!(zero_for_one.swap_fee, U24::from(3997));
+        assert_eq!(zero_for_one.amount1, 995011965097726);
+        assert_eq!(zero_for_one.amount_to_protocol, U256::from(1_000_000_000_000_u64));
+
+        let one_for_zero = pool
+            .simulate_swap(amount(-1_000_000_000_000_000), false, MAX_SQRT_PRICE - U160::ONE)
+            .unwrap();
+        assert_eq!(one_for_zero.swap_fee, U24::from(3499));
+        assert_eq!(one_for_zero.amount0, 995508974311589);
+        assert_eq!(one_for_zero.amount_to_protocol, U256::from(500_000_000_000_u64));
+        assert_eq!(one_for_zero.tick, I24::unchecked_from(19));
+    }
+
+    #[test]
+    fn test_swap_crosses_ticks() {
+        let mut pool = pool(3000, 0);
+
+        let result = pool
+            .swap(amount(-100_000_000_000_000_000), true, MIN_SQRT_PRICE + U160::ONE, None)
+            .unwrap();
+        // the position runs out at -600 and the rest of the range is empty
+        assert_eq!(result.amount0, -30544622242640681);
+        assert_eq!(result.amount1, 29553010879137169);
+        assert_eq!(result.sqrt_price_x96, MIN_SQRT_PRICE + U160::ONE);
+        assert_eq!(result.tick, I24::unchecked_from(MIN_TICK));
+        assert_eq!(result.liquidity, 0);
+        assert_eq!(result.crossed_ticks.len(), 1);
+
+        let crossing = result.crossed_ticks[0];
+        assert_eq!(crossing.tick, I24::unchecked_from(-600));
+        assert_eq!(pool.liquidity, 0);
+        assert_eq!(pool.slot0.tick, I24::unchecked_from(MIN_TICK));
+        assert_eq!(pool.fee_growth_global0_x128, result.fee_growth_global_x128);
+        assert_eq!(pool.ticks[&crossing.tick].fee_growth_outside0_x128, crossing.fee_growth_global0_x128);
+
+        assert!(
+            pool.simulate_swap(amount(-1), true, MIN_SQRT_PRICE + U160::ONE)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_simulate_swap_within_loaded_ticks() {
+        let mut pool = pool(3000, 0);
+        pool.loaded_ticks = I24::unchecked_from(-1200)..=I24::unchecked_from(1200);
+
+        // ends within the loaded ticks
+        let result = pool
+            .simulate_swap(amount(-1_000_000_000_000_000), true, MIN_SQRT_PRICE + U160::ONE)
+            .unwrap();
+        assert_eq!(result.tick, I24::unchecked_from(-20));
+
+        // past -600 the pool is empty, and beyond -1200 it is unknown
+        assert!(
+            pool.simulate_swap(amount(-100_000_000_000_000_000), true, MIN_SQRT_PRICE + U160::ONE)
+                .is_err()
+        );
+        assert!(
+            pool.simulate_swap(amount(-100_000_000_000_000_000), false, MAX_SQRT_PRICE - U160::ONE)
+                .is_err()
+        );
+
+        // a limit at the edge of the loaded ticks stops the swap in time
+        let limit = get_sqrt_price_at_tick(I24::unchecked_from(-1200)).unwrap();
+        let result = pool
+            .simulate_swap(amount(-100_000_000_000_000_000), true, limit)
+            .unwrap();
+        assert_eq!(result.sqrt_price_x96, limit);
+        assert_eq!(result.crossed_ticks.len(), 1);
+    }
+
+    #[test]
+    fn test_simulate_swap_fee_override() {
+        let pool = pool(0, 0);
+
+        let limit = MIN_SQRT_PRICE + U160::ONE;
+
+        let result = pool
+            .simulate_swap_with_lp_fee_override(amount(-1_000_000_000_000_000), true, limit, Some(U24::from(3000)))
+            .unwrap();
+        assert_eq!(result.amount1, 996006981039903);
+
+        assert!(
+            pool.simulate_swap_with_lp_fee_override(amount(1), true, limit, Some(U24::from(1_000_000)))
+                .is_err()
+        );
+        assert!(
+            pool.simulate_swap_with_lp_fee_override(amount(-1), true, limit, Some(U24::from(1_000_001)))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_modify_liquidity_and_donate() {
+        let mut pool = pool(3000, 0);
+        let bits = |pool: &V4PoolState, word_pos: i16| pool.tick_bitmap.get(&word_pos).map(|word| word.0);
+
+        pool.modify_liquidity(I24::unchecked_from(-120), I24::unchecked_from(120), ONE_ETHER as i128 / 2)
+            .unwrap();
+        assert_eq!(pool.liquidity, ONE_ETHER * 3 / 2);
+        assert_eq!(pool.ticks[&I24::unchecked_from(-120)].liquidity_net, ONE_ETHER as i128 / 2);
+        assert_eq!(pool.ticks[&I24::unchecked_from(120)].liquidity_net, -(ONE_ETHER as i128) / 2);
+        assert_eq!(bits(&pool, -1), Some((U256::ONE << 246) | (U256::ONE << 254)));
+        assert_eq!(bits(&pool, 0), Some((U256::ONE << 10) | (U256::ONE << 2)));
+
+        pool.donate(U256::from(ONE_ETHER * 3 / 2), U256::ZERO)
+            .unwrap();
+        assert_eq!(pool.fee_growth_global0_x128, U256::ONE << 128);
+        assert_eq!(pool.fee_growth_global1_x128, U256::ZERO);
+
+        pool.modify_liquidity(I24::unchecked_from(-120), I24::unchecked_from(120), -(ONE_ETHER as i128) / 2)
+            .unwrap();
+        assert_eq!(pool.liquidity, ONE_ETHER);
+        assert_eq!(pool.ticks.len(), 2);
+        assert_eq!(bits(&pool, -1), Some(U256::ONE << 246));
+        assert_eq!(bits(&pool, 0), Some(U256::ONE << 10));
+
+        // ticks at or below the current one start with all the growth so far
+        // outside, and a range ending at the current tick is not active
+        pool.modify_liquidity(I24::unchecked_from(-60), I24::ZERO, 1)
+            .unwrap();
+        assert_eq!(pool.liquidity, ONE_ETHER);
+        assert_eq!(pool.ticks[&I24::unchecked_from(-60)].fee_growth_outside0_x128, U256::ONE << 128);
+        assert_eq!(pool.ticks[&I24::ZERO].fee_growth_outside0_x128, U256::ONE << 128);
+        pool.modify_liquidity(I24::unchecked_from(60), I24::unchecked_from(120), 1)
+            .unwrap();
+        assert_eq!(pool.ticks[&I24::unchecked_from(60)].fee_growth_outside0_x128, U256::ZERO);
+
+        assert!(
+            pool.modify_liquidity(I24::unchecked_from(-30), I24::unchecked_from(60), 1)
+                .is_err()
+        );
+        assert!(
+            pool.modify_liquidity(I24::unchecked_from(-600), I24::unchecked_from(600), -(ONE_ETHER as i128) - 1)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,608 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use alloy_eips::{BlockId, BlockNumHash};
+use alloy_primitives::{
+    Address, B256, I256, Log, U160, U256,
+    aliases::{I24, U24}
+};
+use alloy_sol_types::{SolEvent, sol};
+
+use crate::{
+    BlockNumHashFetcher, StorageSlotFetcher,
+    types::TickData,
+    v4::{
+        UnpackedSlot0, V4PoolKey, V4PoolSnapshot, V4PoolState, V4SwapResult,
+        math::{
+            fees::{MAX_LP_FEE, PIPS_DENOMINATOR, calculate_swap_fee, get_one_for_zero_fee, get_zero_for_one_fee},
+            tick_math::{MAX_SQRT_PRICE, MIN_SQRT_PRICE}
+        },
+        pool_manager::tick_bitmap::{TickBitmap, tick_position_from_compressed}
+    }
+};
+
+sol! {
+    interface IPoolManagerEvents {
+        event ModifyLiquidity(
+            bytes32 indexed id,
+            address indexed sender,
+            int24 tickLower,
+            int24 tickUpper,
+            int256 liquidityDelta,
+            bytes32 salt
+        );
+        event Swap(
+            bytes32 indexed id,
+            address indexed sender,
+            int128 amount0,
+            int128 amount1,
+            uint160 sqrtPriceX96,
+            uint128 liquidity,
+            int24 tick,
+            uint24 fee
+        );
+        event Donate(bytes32 indexed id, address indexed sender, uint256 amount0, uint256 amount1);
+        event ProtocolFeeUpdated(bytes32 indexed id, uint24 protocolFee);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct V4PoolTrackerConfig {
+    /// how many blocks back the tracker can unwind to
+    pub max_reorg_depth: usize,
+    /// blocks between checks against storage, see
+    /// [`V4PoolTracker::is_verification_due`]. 0 never asks for one
+    pub verify_interval: u64
+}
+
+impl Default for V4PoolTrackerConfig {
+    fn default() -> Self {
+        Self { max_reorg_depth: 64, verify_interval: 300 }
+    }
+}
+
+/// where a tracked pool no longer matches storage
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct V4PoolDrift {
+    pub slot0:             bool,
+    pub liquidity:         bool,
+    pub fee_growth_global: bool,
+    /// ticks that differ, including ones only one side has initialized
+    pub ticks:             Vec<I24>,
+    pub tick_bitmap_words: Vec<i16>
+}
+
+impl V4PoolDrift {
+    pub fn between(tracked: &V4PoolState, actual: &V4PoolState) -> Self {
+        let mut ticks = tracked
+            .ticks
+            .keys()
+            .chain(actual.ticks.keys())
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|tick| tracked.ticks.get(tick) != actual.ticks.get(tick))
+            .collect::<Vec<_>>();
+        ticks.sort();
+
+        let word = |state: &V4PoolState, word_pos: &i16| state.tick_bitmap.get(word_pos).map(|word| word.0);
+        let mut tick_bitmap_words = tracked
+            .tick_bitmap
+            .keys()
+            .chain(actual.tick_bitmap.keys())
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|word_pos| word(tracked, word_pos) != word(actual, word_pos))
+            .collect::<Vec<_>>();
+        tick_bitmap_words.sort();
+
+        Self {
+            slot0: tracked.slot0 != actual.slot0,
+            liquidity: tracked.liquidity != actual.liquidity,
+            fee_growth_global: (tracked.fee_growth_global0_x128, tracked.fee_growth_global1_x128)
+                != (actual.fee_growth_global0_x128, actual.fee_growth_global1_x128),
+            ticks,
+            tick_bitmap_words
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// what a block changed, to put back if it is reorged out
+#[derive(Debug, Clone)]
+struct BlockUndo {
+    /// the head before the block
+    parent:                  BlockNumHash,
+    slot0:                   UnpackedSlot0,
+    liquidity:               u128,
+    fee_growth_global0_x128: U256,
+    fee_growth_global1_x128: U256,
+    /// the ticks the block touched, as they were before it
+    ticks:                   HashMap<I24, Option<TickData>>,
+    tick_bitmap:             HashMap<i16, Option<TickBitmap>>
+}
+
+impl BlockUndo {
+    fn new(parent: BlockNumHash, state: &V4PoolState) -> Self {
+        Self {
+            parent,
+            slot0: state.slot0,
+            liquidity: state.liquidity,
+            fee_growth_global0_x128: state.fee_growth_global0_x128,
+            fee_growth_global1_x128: state.fee_growth_global1_x128,
+            ticks: HashMap::new(),
+            tick_bitmap: HashMap::new()
+        }
+    }
+
+    /// keeps `tick` and its bitmap word as they were before the block's
+    /// first change to them
+    fn record_tick(&mut self, state: &V4PoolState, tick: I24) {
+        self.ticks
+            .entry(tick)
+            .or_insert_with(|| state.ticks.get(&tick).copied());
+
+        let (word_pos, _) = tick_position_from_compressed(tick, state.tick_spacing);
+        self.tick_bitmap
+            .entry(word_pos)
+            .or_insert_with(|| state.tick_bitmap.get(&word_pos).copied());
+    }
+
+    fn undo(self, state: &mut V4PoolState) {
+        state.slot0 = self.slot0;
+        state.liquidity = self.liquidity;
+        state.fee_growth_global0_x128 = self.fee_growth_global0_x128;
+        state.fee_growth_global1_x128 = self.fee_growth_global1_x128;
+
+        for (tick, data) in self.ticks {
+            match data {
+                Some(data) => state.ticks.insert(tick, data),
+                None => state.ticks.remove(&tick)
+            };
+        }
+        for (word_pos, word) in self.tick_bitmap {
+            match word {
+                Some(word) => state.tick_bitmap.insert(word_pos, word),
+                None => state.tick_bitmap.remove(&word_pos)
+            };
+        }
+    }
+}
+
+/// keeps a v4 pool up to date block by block from the `PoolManager`'s
+/// `Swap`, `ModifyLiquidity`, `Donate` and `ProtocolFeeUpdated` logs,
+/// starting from a [`V4PoolSnapshot`].
+///
+/// swaps are replayed on the tracked state to find the ticks they crossed and
+/// the fees they paid, since the logs only carry where the pool ended up. lp
+/// fee updates of dynamic fee pools emit no log, so slot0's lp fee can go
+/// stale, and a swap that could be replayed more than one way can leave the
+/// fee growths a few wei off. [`Self::verify`] catches both
+#[derive(Debug, Clone)]
+pub struct V4PoolTracker {
+    pool_manager:        Address,
+    pool_key:            V4PoolKey,
+    head:                BlockNumHash,
+    state:               V4PoolState,
+    journal:             VecDeque<BlockUndo>,
+    config:              V4PoolTrackerConfig,
+    blocks_since_verify: u64
+}
+
+impl V4PoolTracker {
+    pub fn new(pool_manager: Address, snapshot: V4PoolSnapshot, config: V4PoolTrackerConfig) -> Self {
+        Self {
+            pool_manager,
+            pool_key: snapshot.pool_key,
+            head: snapshot.block(),
+            state: snapshot.pool_state(),
+            journal: VecDeque::new(),
+            config,
+            blocks_since_verify: 0
+        }
+    }
+
+    pub fn pool_key(&self) -> V4PoolKey {
+        self.pool_key
+    }
+
+    pub fn pool_id(&self) -> B256 {
+        self.pool_key.into()
+    }
+
+    /// the last block applied
+    pub fn head(&self) -> BlockNumHash {
+        self.head
+    }
+
+    pub fn state(&self) -> &V4PoolState {
+        &self.state
+    }
+
+    /// applies the pool's events in `logs`, the logs of `block` in order.
+    /// logs of other contracts and pools are skipped. `block` must be the
+    /// child of the head, unwind with [`Self::unwind_to`] on a reorg first.
+    /// if an event can't be applied the block is not applied at all
+    pub fn apply_block<'a>(
+        &mut self,
+        block: BlockNumHash,
+        parent_hash: B256,
+        logs: impl IntoIterator<Item = &'a Log>
+    ) -> eyre::Result<()> {
+        if block.number != self.head.number + 1 || parent_hash != self.head.hash {
+            eyre::bail!(
+                "block {} ({}) does not extend the tracked head {} ({})",
+                block.number,
+                block.hash,
+                self.head.number,
+                self.head.hash
+            );
+        }
+
+        let pool_id = self.pool_id();
+        let mut undo = BlockUndo::new(self.head, &self.state);
+        for log in logs {
+            if log.address != self.pool_manager || log.topics().get(1) != Some(&pool_id) {
+                continue;
+            }
+
+            if let Err(e) = self.apply_log(log, &mut undo) {
+                undo.undo(&mut self.state);
+                return Err(e.wrap_err(format!("failed to apply a log of block {}", block.number)));
+            }
+        }
+
+        self.journal.push_back(undo);
+        while self.journal.len() > self.config.max_reorg_depth {
+            self.journal.pop_front();
+        }
+        self.head = block;
+        self.blocks_since_verify += 1;
+
+        Ok(())
+    }
+
+    /// reverts the blocks applied after `block`, making it the head. fails
+    /// without changing anything if `block` is not one of the journaled
+    /// ancestors of the head
+    pub fn unwind_to(&mut self, block: BlockNumHash) -> eyre::Result<()> {
+        if block == self.head {
+            return Ok(());
+        }
+
+        let Some(depth) = self
+            .journal
+            .iter()
+            .rev()
+            .position(|undo| undo.parent == block)
+        else {
+            eyre::bail!(
+                "block {} ({}) is not within the last {} blocks tracked",
+                block.number,
+                block.hash,
+                self.journal.len()
+            );
+        };
+
+        for _ in 0..=depth {
+            let undo = self
+                .journal
+                .pop_back()
+                .expect("depth is within the journal");
+            undo.undo(&mut self.state);
+        }
+        self.head = block;
+
+        Ok(())
+    }
+
+    /// if [`V4PoolTrackerConfig::verify_interval`] blocks were applied since
+    /// the tracker was created or last verified
+    pub fn is_verification_due(&self) -> bool {
+        self.config.verify_interval != 0 && self.blocks_since_verify >= self.config.verify_interval
+    }
+
+    /// reads the pool from storage at the head and compares it to the tracked
+    /// state. the tracked state is kept either way, see [`Self::reset`]
+    pub async fn verify<F>(&mut self, fetcher: &F) -> eyre::Result<V4PoolDrift>
+    where
+        F: StorageSlotFetcher + BlockNumHashFetcher
+    {
+        let snapshot =
+            V4PoolSnapshot::load(fetcher, self.pool_manager, self.pool_key, BlockId::hash(self.head.hash)).await?;
+        self.blocks_since_verify = 0;
+
+        Ok(V4PoolDrift::between(&self.state, &snapshot.pool_state()))
+    }
+
+    /// starts over from `snapshot`. blocks before it can no longer be unwound
+    pub fn reset(&mut self, snapshot: V4PoolSnapshot) {
+        *self = Self::new(self.pool_manager, snapshot, self.config);
+    }
+
+    fn apply_log(&mut self, log: &Log, undo: &mut BlockUndo) -> eyre::Result<()> {
+        let Some(topic) = log.topics().first() else { return Ok(()) };
+
+        match *topic {
+            IPoolManagerEvents::Swap::SIGNATURE_HASH => {
+                let event = IPoolManagerEvents::Swap::decode_log_data(&log.data)?;
+                let result = self.replay_swap(&event)?;
+                for crossing in &result.crossed_ticks {
+                    undo.record_tick(&self.state, crossing.tick);
+                }
+                self.state.apply_swap(&result);
+            }
+            IPoolManagerEvents::ModifyLiquidity::SIGNATURE_HASH => {
+                let event = IPoolManagerEvents::ModifyLiquidity::decode_log_data(&log.data)?;
+                let liquidity_delta = i128::try_from(event.liquidityDelta)
+                    .map_err(|_| eyre::eyre!("SafeCastOverflow: {} is not an int128", event.liquidityDelta))?;
+                undo.record_tick(&self.state, event.tickLower);
+                undo.record_tick(&self.state, event.tickUpper);
+                self.state
+                    .modify_liquidity(event.tickLower, event.tickUpper, liquidity_delta)?;
+            }
+            IPoolManagerEvents::Donate::SIGNATURE_HASH => {
+                let event = IPoolManagerEvents::Donate::decode_log_data(&log.data)?;
+                self.state.donate(event.amount0, event.amount1)?;
+            }
+            IPoolManagerEvents::ProtocolFeeUpdated::SIGNATURE_HASH => {
+                let event = IPoolManagerEvents::ProtocolFeeUpdated::decode_log_data(&log.data)?;
+                self.state.slot0.protocol_fee = event.protocolFee;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// finds the swap that ends where `event` says the pool did. the log
+    /// doesn't say whether the input or the output was specified, or what
+    /// price limit was set, so each is tried in turn. a swap that stopped at
+    /// its price limit is replayed as an unbounded exact input limited to
+    /// where it stopped, which takes the same steps whether it was an exact
+    /// input or output
+    fn replay_swap(&self, event: &IPoolManagerEvents::Swap) -> eyre::Result<V4SwapResult> {
+        let zero_for_one = event.amount0 < 0 || event.amount1 > 0;
+        let (amount_in, amount_out) =
+            if zero_for_one { (event.amount0, event.amount1) } else { (event.amount1, event.amount0) };
+
+        let protocol_fee = if zero_for_one {
+            get_zero_for_one_fee(self.state.slot0.protocol_fee)
+        } else {
+            get_one_for_zero_fee(self.state.slot0.protocol_fee)
+        };
+        let lp_fee_override = lp_fee_for_swap_fee(protocol_fee, self.state.slot0.lp_fee, event.fee)
+            .ok_or_else(|| eyre::eyre!("no lp fee gives the swap fee {}", event.fee))?;
+
+        let price_bound = if zero_for_one { MIN_SQRT_PRICE + U160::ONE } else { MAX_SQRT_PRICE - U160::ONE };
+        let candidates = [
+            (I256::unchecked_from(amount_in), price_bound),
+            (I256::unchecked_from(amount_out), price_bound),
+            (-I256::MAX, event.sqrtPriceX96)
+        ];
+
+        candidates
+            .into_iter()
+            .filter_map(|(amount_specified, sqrt_price_limit_x96)| {
+                self.state
+                    .simulate_swap_with_lp_fee_override(
+                        amount_specified,
+                        zero_for_one,
+                        sqrt_price_limit_x96,
+                        lp_fee_override
+                    )
+                    .ok()
+            })
+            .find(|result| {
+                result.amount0 == event.amount0
+                    && result.amount1 == event.amount1
+                    && result.sqrt_price_x96 == event.sqrtPriceX96
+                    && result.tick == event.tick
+                    && result.liquidity == event.liquidity
+                    && result.swap_fee == event.fee
+            })
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "no swap on the tracked state ends at sqrt price {} with amounts ({}, {})",
+                    event.sqrtPriceX96,
+                    event.amount0,
+                    event.amount1
+                )
+            })
+    }
+}
+
+/// the lp fee to swap with for the pool to charge `swap_fee`, `None` when
+/// slot0's does. since the lp fee only enters a swap through the swap fee, any
+/// lp fee that gives it replays the same
+fn lp_fee_for_swap_fee(protocol_fee: u16, slot0_lp_fee: U24, swap_fee: U24) -> Option<Option<U24>> {
+    let charged = |lp_fee: U24| if protocol_fee == 0 { lp_fee } else { calculate_swap_fee(protocol_fee, lp_fee) };
+    if charged(slot0_lp_fee) == swap_fee {
+        return Some(None);
+    }
+
+    // swap fee = protocol fee + lp fee * (1 - protocol fee), rounded up
+    let protocol_fee = protocol_fee as u64;
+    let guess = swap_fee.to::<u64>().saturating_sub(protocol_fee) * PIPS_DENOMINATOR as u64
+        / (PIPS_DENOMINATOR as u64 - protocol_fee);
+    (guess.saturating_sub(2)..=guess + 2)
+        .filter(|lp_fee| *lp_fee <= MAX_LP_FEE as u64)
+        .map(U24::from)
+        .find(|lp_fee| charged(*lp_fee) == swap_fee)
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+
+    use super::*;
+    use crate::v4::math::tick_math::{MAX_TICK, MIN_TICK, get_sqrt_price_at_tick};
+
+    const ONE_ETHER: u128 = 1_000_000_000_000_000_000;
+    const POOL_MANAGER: Address = address!("0x000000000004444c5dc75cB358380D2e3dE08A90");
+
+    fn block(number: u64) -> BlockNumHash {
+        BlockNumHash::new(number, B256::with_last_byte(number as u8))
+    }
+
+    /// one position of 1e18 liquidity over [-600, 600], at price 1
+    fn snapshot() -> V4PoolSnapshot {
+        let pool_key = V4PoolKey {
+            currency0:   address!("0x0000000000000000000000000000000000000001"),
+            currency1:   address!("0x0000000000000000000000000000000000000002"),
+            fee:         U24::from(3000),
+            tickSpacing: I24::unchecked_from(60),
+            hooks:       Address::ZERO
+        };
+        let state = V4PoolState::new(
+            pool_key.tickSpacing,
+            UnpackedSlot0 {
+                sqrt_price_x96: U160::ONE << 96,
+                tick:           I24::ZERO,
+                protocol_fee:   U24::ZERO,
+                lp_fee:         U24::from(3000)
+            },
+            ONE_ETHER,
+            (U256::ZERO, U256::ZERO),
+            I24::unchecked_from(MIN_TICK)..=I24::unchecked_from(MAX_TICK),
+            [(-600, ONE_ETHER as i128), (600, -(ONE_ETHER as i128))].map(|(tick, liquidity_net)| TickData {
+                tick: I24::unchecked_from(tick),
+                is_initialized: true,
+                liquidity_net,
+                liquidity_gross: ONE_ETHER,
+                fee_growth_outside0_x128: U256::ZERO,
+                fee_growth_outside1_x128: U256::ZERO
+            })
+        );
+
+        V4PoolSnapshot {
+            block_number: 1,
+            block_hash: block(1).hash,
+            pool_key,
+            slot0: state.slot0,
+            liquidity: state.liquidity,
+            fee_growth_global0_x128: state.fee_growth_global0_x128,
+            fee_growth_global1_x128: state.fee_growth_global1_x128,
+            tick_bitmap: state.tick_bitmap,
+            ticks: state.ticks
+        }
+    }
+
+    fn log(event: impl SolEvent) -> Log {
+        Log { address: POOL_MANAGER, data: event.encode_log_data() }
+    }
+
+    fn swap_log(pool_id: B256, result: &V4SwapResult) -> Log {
+        log(IPoolManagerEvents::Swap {
+            id:           pool_id,
+            sender:       Address::ZERO,
+            amount0:      result.amount0,
+            amount1:      result.amount1,
+            sqrtPriceX96: result.sqrt_price_x96,
+            liquidity:    result.liquidity,
+            tick:         result.tick,
+            fee:          result.swap_fee
+        })
+    }
+
+    #[test]
+    fn test_tracker_applies_and_unwinds_blocks() {
+        let snapshot = snapshot();
+        let pool_id = snapshot.pool_id();
+        let mut tracker = V4PoolTracker::new(POOL_MANAGER, snapshot.clone(), V4PoolTrackerConfig::default());
+        let mut expected = snapshot.pool_state();
+
+        let range = (I24::unchecked_from(-120), I24::unchecked_from(120));
+        expected
+            .modify_liquidity(range.0, range.1, ONE_ETHER as i128)
+            .unwrap();
+        let modify_liquidity = log(IPoolManagerEvents::ModifyLiquidity {
+            id:             pool_id,
+            sender:         Address::ZERO,
+            tickLower:      range.0,
+            tickUpper:      range.1,
+            liquidityDelta: I256::try_from(ONE_ETHER).unwrap(),
+            salt:           B256::ZERO
+        });
+        // an exact output that crosses 120
+        let exact_out = expected
+            .swap(I256::try_from(20_000_000_000_000_000_u128).unwrap(), false, MAX_SQRT_PRICE - U160::ONE, None)
+            .unwrap();
+        assert_eq!(exact_out.crossed_ticks.len(), 1);
+        let other_pool = log(IPoolManagerEvents::Donate {
+            id:      B256::ZERO,
+            sender:  Address::ZERO,
+            amount0: U256::ONE,
+            amount1: U256::ONE
+        });
+        tracker
+            .apply_block(block(2), block(1).hash, &[modify_liquidity, swap_log(pool_id, &exact_out), other_pool])
+            .unwrap();
+        assert!(V4PoolDrift::between(tracker.state(), &expected).is_empty());
+        let after_block_2 = expected.clone();
+
+        // stops at its price limit, past every position
+        let limit = get_sqrt_price_at_tick(I24::unchecked_from(-700)).unwrap();
+        let limited = expected.swap(-I256::MAX, true, limit, None).unwrap();
+        assert_eq!(limited.crossed_ticks.len(), 3);
+        assert_eq!(limited.liquidity, 0);
+        let donate = log(IPoolManagerEvents::Donate {
+            id:      pool_id,
+            sender:  Address::ZERO,
+            amount0: U256::ONE,
+            amount1: U256::ZERO
+        });
+        // nothing is in range to donate to, so the whole block fails
+        assert!(
+            tracker
+                .apply_block(block(3), block(2).hash, &[swap_log(pool_id, &limited), donate])
+                .is_err()
+        );
+        assert!(V4PoolDrift::between(tracker.state(), &after_block_2).is_empty());
+
+        tracker
+            .apply_block(block(3), block(2).hash, &[swap_log(pool_id, &limited)])
+            .unwrap();
+        assert!(V4PoolDrift::between(tracker.state(), &expected).is_empty());
+        assert_eq!(tracker.state().slot0.tick, I24::unchecked_from(-700));
+
+        assert!(tracker.apply_block(block(5), block(4).hash, []).is_err());
+        assert!(tracker.unwind_to(block(0)).is_err());
+
+        tracker.unwind_to(block(2)).unwrap();
+        assert_eq!(tracker.head(), block(2));
+        assert!(V4PoolDrift::between(tracker.state(), &after_block_2).is_empty());
+
+        tracker.unwind_to(block(1)).unwrap();
+        assert!(V4PoolDrift::between(tracker.state(), &snapshot.pool_state()).is_empty());
+    }
+
+    #[test]
+    fn test_tracker_infers_overridden_lp_fee() {
+        let snapshot = snapshot();
+        let pool_id = snapshot.pool_id();
+        let mut tracker = V4PoolTracker::new(POOL_MANAGER, snapshot.clone(), V4PoolTrackerConfig::default());
+
+        let mut expected = snapshot.pool_state();
+        expected.slot0.protocol_fee = U24::from((500 << 12) | 1000);
+        let protocol_fee_updated =
+            log(IPoolManagerEvents::ProtocolFeeUpdated { id: pool_id, protocolFee: expected.slot0.protocol_fee });
+        // the hook overrides the lp fee to 500
+        let amount = I256::try_from(-1_000_000_000_000_000_i128).unwrap();
+        let swap = expected
+            .swap(amount, true, MIN_SQRT_PRICE + U160::ONE, Some(U24::from(500)))
+            .unwrap();
+        tracker
+            .apply_block(block(2), block(1).hash, &[protocol_fee_updated, swap_log(pool_id, &swap)])
+            .unwrap();
+        assert!(V4PoolDrift::between(tracker.state(), &expected).is_empty());
+
+        assert_eq!(lp_fee_for_swap_fee(1000, U24::from(3000), U24::from(3997)), Some(None));
+        assert_eq!(lp_fee_for_swap_fee(0, U24::from(3000), U24::from(500)), Some(Some(U24::from(500))));
+        assert_eq!(lp_fee_for_swap_fee(1000, U24::from(3000), U24::from(999)), None);
+    }
+}